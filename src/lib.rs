@@ -1,5 +1,15 @@
+extern crate chrono;
+#[cfg(feature = "encoding_rs")]
+extern crate encoding_rs;
 extern crate frontmatter;
 extern crate hoedown;
+extern crate notify;
+#[cfg(feature = "serde")]
+extern crate serde;
+extern crate sha2;
+extern crate toml;
+#[cfg(feature = "tokio")]
+extern crate tokio;
 extern crate walkdir;
 extern crate yaml_rust;
 
@@ -0,0 +1,49 @@
+use std::future::Future;
+use std::io;
+use std::panic;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::task::{self, JoinHandle};
+
+use super::{Wiki, WikiError};
+
+/// Loads a wiki on the blocking thread pool of the tokio runtime, see
+/// `Wiki::new_async`. Nothing happens until the future is polled
+pub struct LoadWiki {
+    /// the root path of the wiki, taken once loading has started
+    path: Option<String>,
+    /// the task loading the wiki, once it has been spawned
+    handle: Option<JoinHandle<Result<Wiki, WikiError>>>,
+}
+
+impl LoadWiki {
+    /// Creates the future loading the wiki at the given path
+    pub fn new(pathname: &str) -> LoadWiki {
+        LoadWiki { path: Some(String::from(pathname)), handle: None }
+    }
+}
+
+impl Future for LoadWiki {
+    type Output = Result<Wiki, WikiError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        if let Some(path) = self.path.take() {
+            // reading the files and rendering the pages both block, so the
+            // whole load runs as a single blocking task
+            self.handle = Some(task::spawn_blocking(move || Wiki::new(path.as_str())));
+        }
+        let handle = self.handle.as_mut().expect("polled after completion");
+        match Pin::new(handle).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(result) => {
+                self.handle = None;
+                Poll::Ready(match result {
+                    Ok(wiki) => wiki,
+                    Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+                    Err(e) => Err(WikiError::Io(io::Error::other(e)))
+                })
+            }
+        }
+    }
+}
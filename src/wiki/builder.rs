@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{DEFAULT_EXTENSION, DEFAULT_INDEX_NAME, FrontmatterFormat, MarkdownOptions, Page,
+            Template, UrlMatching, Wiki, WikiError};
+
+/// Configures and loads a `Wiki`, i.e.
+/// `WikiBuilder::default().path("docs").index_name("readme").build()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct WikiBuilder {
+    /// the root path of the wiki
+    path: PathBuf,
+    /// the file extensions of pages without the leading dot
+    extensions: Vec<String>,
+    /// the options used to render the markdown of all pages
+    markdown_options: MarkdownOptions,
+    /// the name of the page that is used for the URL of a directory
+    index_name: String,
+    /// the template used to render pages as complete HTML documents
+    template: Template,
+    /// how URLs are matched if there is no page with the exact URL
+    url_matching: UrlMatching,
+    /// whether drafts are included in listings, searches and exports
+    include_drafts: bool,
+    /// the format frontmatter of new pages is written in
+    frontmatter_format: FrontmatterFormat,
+    /// whether exports write redirects at the aliases of pages
+    export_redirects: bool,
+    /// glob patterns of the files that are not loaded as pages
+    exclude: Vec<String>,
+    /// how deep below the root files are loaded as pages
+    max_depth: Option<usize>,
+    /// further directories loaded into the same namespace
+    roots: Vec<PathBuf>,
+    /// the URL of the entry point of the wiki
+    home_page: String,
+}
+
+impl Default for WikiBuilder {
+    fn default() -> WikiBuilder {
+        WikiBuilder {
+            path: PathBuf::from("."),
+            extensions: vec![String::from(DEFAULT_EXTENSION)],
+            markdown_options: MarkdownOptions::default(),
+            index_name: String::from(DEFAULT_INDEX_NAME),
+            template: Template::default(),
+            url_matching: UrlMatching::Exact,
+            include_drafts: false,
+            frontmatter_format: FrontmatterFormat::Yaml,
+            export_redirects: false,
+            exclude: Vec::new(),
+            max_depth: None,
+            roots: Vec::new(),
+            home_page: String::from("/"),
+        }
+    }
+}
+
+impl WikiBuilder {
+    /// Sets the root path of the wiki, defaults to the current directory
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> WikiBuilder {
+        self.path = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the file extensions of pages without the leading dot, new
+    /// pages are created using the first one. Defaults to `["md"]`
+    pub fn extensions(mut self, extensions: &[&str]) -> WikiBuilder {
+        self.extensions = extensions.iter().map(|e| String::from(*e)).collect();
+        self
+    }
+
+    /// Sets the options used to render the markdown of all pages
+    pub fn markdown_options(mut self, markdown_options: MarkdownOptions) -> WikiBuilder {
+        self.markdown_options = markdown_options;
+        self
+    }
+
+    /// Sets the name of the page that is used for the URL of a directory,
+    /// defaults to `index`
+    pub fn index_name(mut self, index_name: &str) -> WikiBuilder {
+        self.index_name = String::from(index_name);
+        self
+    }
+
+    /// Sets whether raw HTML contained in the markdown is escaped
+    pub fn sanitize(mut self, sanitize: bool) -> WikiBuilder {
+        self.markdown_options.sanitize = sanitize;
+        self
+    }
+
+    /// Sets the template used to render pages as complete HTML documents
+    pub fn template(mut self, template: Template) -> WikiBuilder {
+        self.template = template;
+        self
+    }
+
+    /// Sets how URLs are matched if there is no page with the exact URL,
+    /// defaults to `UrlMatching::Exact`
+    pub fn url_matching(mut self, url_matching: UrlMatching) -> WikiBuilder {
+        self.url_matching = url_matching;
+        self
+    }
+
+    /// Sets whether drafts are included in listings, searches and exports,
+    /// defaults to `false`
+    pub fn include_drafts(mut self, include_drafts: bool) -> WikiBuilder {
+        self.include_drafts = include_drafts;
+        self
+    }
+
+    /// Sets the format frontmatter of new pages is written in, existing
+    /// pages keep the format of their file. Defaults to
+    /// `FrontmatterFormat::Yaml`
+    pub fn frontmatter_format(mut self, frontmatter_format: FrontmatterFormat) -> WikiBuilder {
+        self.frontmatter_format = frontmatter_format;
+        self
+    }
+
+    /// Sets whether exports write a page redirecting to the canonical page
+    /// at every alias, defaults to `false`
+    pub fn export_redirects(mut self, export_redirects: bool) -> WikiBuilder {
+        self.export_redirects = export_redirects;
+        self
+    }
+
+    /// Sets the glob patterns of the files that are not loaded as pages,
+    /// i.e. `["_*", "node_modules/**"]`. Patterns containing a slash are
+    /// matched against the path relative to the root, others against every
+    /// file and directory name. Defaults to no patterns
+    pub fn exclude(mut self, exclude: &[&str]) -> WikiBuilder {
+        self.exclude = exclude.iter().map(|pattern| String::from(*pattern)).collect();
+        self
+    }
+
+    /// Sets how deep below the root files are loaded as pages, files
+    /// directly in the root are at depth 1. Defaults to `None`, which loads
+    /// the files at any depth
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> WikiBuilder {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets further directories whose pages are loaded into the same
+    /// namespace as those of the root path, pages of later directories win
+    /// over those with the same URL in earlier ones. Defaults to none
+    pub fn roots(mut self, roots: &[&str]) -> WikiBuilder {
+        self.roots = roots.iter().map(PathBuf::from).collect();
+        self
+    }
+
+    /// Sets the URL of the entry point of the wiki, which is never reported
+    /// as an orphan. Defaults to `/`, the index page of the root directory
+    pub fn home_page(mut self, url: &str) -> WikiBuilder {
+        self.home_page = String::from(url);
+        self
+    }
+
+    /// Creates the wiki and loads all of its pages
+    /// # Errors
+    /// This will return an error if the root directory of the wiki can not
+    /// be read, i.e. because it does not exist, or `WikiError::InvalidUrl`
+    /// if its path is not valid utf-8
+    pub fn build(self) -> Result<Wiki, WikiError> {
+        self.check_path()?;
+        let mut wiki = self.into_wiki();
+        wiki.load_pages()?;
+        Ok(wiki)
+    }
+
+    /// Creates the wiki from the given pages instead of loading them from
+    /// its directory, which is never read
+    /// # Errors
+    /// This will return `WikiError::InvalidUrl` if a page does not belong
+    /// to the directory of the wiki or its path is not valid utf-8 and
+    /// `WikiError::AlreadyExists` if several pages share a URL
+    pub fn build_from_pages(self, pages: Vec<Page>) -> Result<Wiki, WikiError> {
+        self.check_path()?;
+        let mut wiki = self.into_wiki();
+        for page in pages {
+            if page.base_path != wiki.path {
+                return Err(WikiError::InvalidUrl(page.url));
+            }
+            if wiki.index.contains_key(page.url.as_str()) {
+                return Err(WikiError::AlreadyExists(page.url));
+            }
+            wiki.index.insert(page.url.clone(), wiki.pages.len());
+            wiki.pages.push(page);
+        }
+        wiki.reindex_aliases();
+        wiki.update_missing_links();
+        Ok(wiki)
+    }
+
+    /// Makes sure the root path is valid utf-8, as the URLs of the pages
+    /// are derived from it
+    fn check_path(&self) -> Result<(), WikiError> {
+        match self.path.to_str() {
+            Some(_) => Ok(()),
+            None => Err(WikiError::InvalidUrl(self.path.to_string_lossy().into_owned()))
+        }
+    }
+
+    /// Creates the wiki without any pages
+    fn into_wiki(self) -> Wiki {
+        Wiki {
+            path: self.path,
+            pages: Vec::new(),
+            markdown_options: self.markdown_options,
+            extensions: self.extensions,
+            index_name: self.index_name,
+            template: self.template,
+            url_matching: self.url_matching,
+            include_drafts: self.include_drafts,
+            frontmatter_format: self.frontmatter_format,
+            export_redirects: self.export_redirects,
+            exclude: self.exclude,
+            max_depth: self.max_depth,
+            roots: self.roots,
+            home_page: self.home_page,
+            watcher: None,
+            index: HashMap::new(),
+            aliases: HashMap::new(),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+/// The known emoji shortcodes without the colons, sorted by name so they
+/// can be looked up with a binary search
+const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "\u{1F44D}"),
+    ("-1", "\u{1F44E}"),
+    ("100", "\u{1F4AF}"),
+    ("angry", "\u{1F620}"),
+    ("arrow_down", "\u{2B07}\u{FE0F}"),
+    ("arrow_left", "\u{2B05}\u{FE0F}"),
+    ("arrow_right", "\u{27A1}\u{FE0F}"),
+    ("arrow_up", "\u{2B06}\u{FE0F}"),
+    ("beer", "\u{1F37A}"),
+    ("bell", "\u{1F514}"),
+    ("blush", "\u{1F60A}"),
+    ("book", "\u{1F4D6}"),
+    ("bookmark", "\u{1F516}"),
+    ("boom", "\u{1F4A5}"),
+    ("bug", "\u{1F41B}"),
+    ("bulb", "\u{1F4A1}"),
+    ("calendar", "\u{1F4C6}"),
+    ("clap", "\u{1F44F}"),
+    ("coffee", "\u{2615}"),
+    ("construction", "\u{1F6A7}"),
+    ("cry", "\u{1F622}"),
+    ("eyes", "\u{1F440}"),
+    ("fire", "\u{1F525}"),
+    ("grin", "\u{1F601}"),
+    ("heart", "\u{2764}\u{FE0F}"),
+    ("heavy_check_mark", "\u{2714}\u{FE0F}"),
+    ("hourglass", "\u{231B}"),
+    ("information_source", "\u{2139}\u{FE0F}"),
+    ("joy", "\u{1F602}"),
+    ("key", "\u{1F511}"),
+    ("laughing", "\u{1F606}"),
+    ("link", "\u{1F517}"),
+    ("lock", "\u{1F512}"),
+    ("mag", "\u{1F50D}"),
+    ("memo", "\u{1F4DD}"),
+    ("no_entry", "\u{26D4}"),
+    ("ok_hand", "\u{1F44C}"),
+    ("pencil2", "\u{270F}\u{FE0F}"),
+    ("pushpin", "\u{1F4CC}"),
+    ("question", "\u{2753}"),
+    ("rocket", "\u{1F680}"),
+    ("sad", "\u{1F61E}"),
+    ("smile", "\u{1F604}"),
+    ("smiley", "\u{1F603}"),
+    ("sparkles", "\u{2728}"),
+    ("star", "\u{2B50}"),
+    ("tada", "\u{1F389}"),
+    ("thinking", "\u{1F914}"),
+    ("thumbsdown", "\u{1F44E}"),
+    ("thumbsup", "\u{1F44D}"),
+    ("warning", "\u{26A0}\u{FE0F}"),
+    ("wave", "\u{1F44B}"),
+    ("white_check_mark", "\u{2705}"),
+    ("wink", "\u{1F609}"),
+    ("wrench", "\u{1F527}"),
+    ("x", "\u{274C}"),
+    ("zap", "\u{26A1}"),
+];
+
+/// Returns the emoji for the shortcode without the colons, i.e. `smile`
+pub fn lookup(name: &str) -> Option<&'static str> {
+    SHORTCODES.binary_search_by(|&(shortcode, _)| shortcode.cmp(name))
+        .ok()
+        .map(|i| SHORTCODES[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lookup, SHORTCODES};
+
+    #[test]
+    fn test_lookup() {
+        assert!(SHORTCODES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(lookup("smile"), Some("\u{1F604}"));
+        assert_eq!(lookup("+1"), Some("\u{1F44D}"));
+        assert_eq!(lookup("Smile"), None);
+        assert_eq!(lookup("unknown"), None);
+    }
+}
@@ -0,0 +1,58 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The errors that can occur while working with a wiki
+#[derive(Debug)]
+pub enum WikiError {
+    /// an error of the underlying file system, i.e. lacking permissions
+    Io(io::Error),
+    /// there is no page with the given URL
+    NotFound(String),
+    /// there already is a page with the given URL
+    AlreadyExists(String),
+    /// the given URL can not be used for a page
+    InvalidUrl(String),
+    /// the frontmatter of a page could not be parsed
+    Frontmatter(String),
+    /// saving the page with the given URL failed
+    Save(String, Box<WikiError>),
+    /// the directory of the wiki can not be watched for changes
+    Watch(String),
+}
+
+impl fmt::Display for WikiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WikiError::Io(ref e) => write!(f, "{}", e),
+            WikiError::NotFound(ref url) =>
+                write!(f, "page {} does not exist", url),
+            WikiError::AlreadyExists(ref url) =>
+                write!(f, "page {} already exists", url),
+            WikiError::InvalidUrl(ref url) =>
+                write!(f, "{} is not a valid page url", url),
+            WikiError::Frontmatter(ref msg) =>
+                write!(f, "invalid frontmatter: {}", msg),
+            WikiError::Save(ref url, ref e) =>
+                write!(f, "could not save page {}: {}", url, e),
+            WikiError::Watch(ref msg) =>
+                write!(f, "could not watch wiki: {}", msg),
+        }
+    }
+}
+
+impl Error for WikiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            WikiError::Io(ref e) => Some(e),
+            WikiError::Save(_, ref e) => Some(e.as_ref()),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for WikiError {
+    fn from(e: io::Error) -> WikiError {
+        WikiError::Io(e)
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+
+/// Escapes the characters that have a special meaning in XML
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+/// Formats the time as a W3C datetime, i.e. `2016-05-01T12:30:00Z`
+pub fn format_w3c(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Formats the time as an RFC 822 date as used by RSS, i.e.
+/// `Sun, 1 May 2016 12:30:00 +0000`
+pub fn format_rfc822(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc2822()
+}
+
+/// Wraps the text in a CDATA section, splitting up any `]]>` it contains
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// Joins the base URL of a site and the URL of a page, encoding spaces
+pub fn join_url(base_url: &str, url: &str) -> String {
+    format!("{}{}", base_url.trim_end_matches('/'), url.replace(' ', "%20"))
+}
+
+/// Builds a sitemap from the absolute URLs of the pages and the time they
+/// were last modified
+pub fn sitemap(entries: &[(String, Option<SystemTime>)]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for &(ref loc, modified) in entries {
+        xml.push_str("  <url>\n");
+        xml.push_str(format!("    <loc>{}</loc>\n", escape(loc)).as_str());
+        if let Some(modified) = modified {
+            xml.push_str(format!("    <lastmod>{}</lastmod>\n", format_w3c(modified)).as_str());
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// An item of an RSS feed
+pub struct FeedItem {
+    /// the title of the item
+    pub title: String,
+    /// the absolute URL of the item
+    pub link: String,
+    /// the HTML content of the item
+    pub description: String,
+    /// the time the item was published or last modified
+    pub published: Option<SystemTime>,
+}
+
+/// Builds an RSS 2.0 document for the channel at the given URL
+pub fn rss(title: &str, link: &str, items: &[FeedItem]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+    xml.push_str(format!("  <title>{}</title>\n", escape(title)).as_str());
+    xml.push_str(format!("  <link>{}</link>\n", escape(link)).as_str());
+    xml.push_str(format!("  <description>{}</description>\n", escape(title)).as_str());
+    for item in items {
+        xml.push_str("  <item>\n");
+        xml.push_str(format!("    <title>{}</title>\n", escape(item.title.as_str())).as_str());
+        xml.push_str(format!("    <link>{}</link>\n", escape(item.link.as_str())).as_str());
+        xml.push_str(format!("    <guid>{}</guid>\n", escape(item.link.as_str())).as_str());
+        if let Some(published) = item.published {
+            xml.push_str(format!("    <pubDate>{}</pubDate>\n", format_rfc822(published)).as_str());
+        }
+        xml.push_str(format!("    <description>{}</description>\n",
+                             cdata(item.description.as_str())).as_str());
+        xml.push_str("  </item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+    use super::{cdata, escape, format_rfc822, format_w3c};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<a href=\"x\">Tom & Jerry's</a>"),
+                   "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;")
+    }
+
+    #[test]
+    fn test_format_w3c() {
+        assert_eq!(format_w3c(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(format_w3c(UNIX_EPOCH + Duration::from_secs(951_827_696)),
+                   "2000-02-29T12:34:56Z");
+        assert_eq!(format_w3c(UNIX_EPOCH + Duration::from_secs(1_735_689_599)),
+                   "2024-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_format_rfc822() {
+        assert_eq!(format_rfc822(UNIX_EPOCH), "Thu, 1 Jan 1970 00:00:00 +0000");
+        assert_eq!(format_rfc822(UNIX_EPOCH + Duration::from_secs(951_827_696)),
+                   "Tue, 29 Feb 2000 12:34:56 +0000");
+    }
+
+    #[test]
+    fn test_cdata() {
+        assert_eq!(cdata("<p>a]]>b</p>"), "<![CDATA[<p>a]]]]><![CDATA[>b</p>]]>")
+    }
+}
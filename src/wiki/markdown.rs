@@ -0,0 +1,1083 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use super::emoji;
+
+/// A heading found in a markdown document
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heading {
+    /// the level of the heading, 1 to 6
+    pub level: u8,
+    /// the text of the heading
+    pub text: String,
+}
+
+/// Returns whether the given line opens or closes a fenced code block
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// Returns whether the line is a table of contents marker, `[TOC]` or
+/// `[[toc]]` on a line of its own, ignoring case
+pub fn is_toc_marker(line: &str) -> bool {
+    let line = line.trim();
+    line.eq_ignore_ascii_case("[toc]") || line.eq_ignore_ascii_case("[[toc]]")
+}
+
+/// Returns whether the markdown contains a table of contents marker
+/// outside of fenced code blocks
+pub fn has_toc_marker(markdown: &str) -> bool {
+    let mut in_fence = false;
+    markdown.lines().any(|line| {
+        if is_fence(line) {
+            in_fence = !in_fence;
+        }
+        !in_fence && is_toc_marker(line)
+    })
+}
+
+/// Parses an ATX heading like `## Heading ##`
+fn parse_atx_heading(line: &str) -> Option<Heading> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = line.trim_start_matches(' ');
+    let level = line.len() - line.trim_start_matches('#').len();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') && !rest.starts_with('\t') {
+        return None;
+    }
+    let text = rest.trim().trim_end_matches('#').trim();
+    Some(Heading { level: level as u8, text: String::from(text) })
+}
+
+/// Returns the level of a setext underline, `===` for 1 and `---` for 2
+fn setext_level(line: &str) -> Option<u8> {
+    let line = line.trim();
+    if line.is_empty() {
+        None
+    } else if line.chars().all(|c| c == '=') {
+        Some(1)
+    } else if line.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Extracts all ATX and setext headings from the markdown, ignoring
+/// anything inside of fenced code blocks
+pub fn headings(markdown: &str) -> Vec<Heading> {
+    heading_ranges(markdown).into_iter().map(|(_, heading)| heading).collect()
+}
+
+/// Same as `headings`, but also returns the range of the lines making up
+/// every heading including their line breaks
+fn heading_ranges(markdown: &str) -> Vec<(Range<usize>, Heading)> {
+    let mut headings = Vec::new();
+    let mut in_fence = false;
+    let mut previous: Option<(usize, &str)> = None;
+    let mut start = 0;
+
+    for line in markdown.split_inclusive('\n') {
+        let end = start + line.len();
+        let line_start = start;
+        start = end;
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if is_fence(line) {
+            in_fence = !in_fence;
+            previous = None;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some(heading) = parse_atx_heading(line) {
+            headings.push((line_start..end, heading));
+            previous = None;
+            continue;
+        }
+        if let (Some((text_start, text)), Some(level)) = (previous, setext_level(line)) {
+            headings.push((text_start..end, Heading { level, text: String::from(text.trim()) }));
+            previous = None;
+            continue;
+        }
+        previous = if line.trim().is_empty() || line.starts_with("    ") {
+            None
+        } else {
+            Some((line_start, line))
+        };
+    }
+    headings
+}
+
+/// Inserts the content into the section of the markdown below the first
+/// heading with the given text, either before or after the existing
+/// content of the section. The section ends at the next heading of the
+/// same or a higher level. If there is no such heading, a level 2 heading
+/// with the content is added to the end of the markdown
+pub fn insert_section(markdown: &str, heading: &str, content: &str, append: bool) -> String {
+    let content = content.trim_matches('\n');
+    let heading = heading.trim();
+    let headings = heading_ranges(markdown);
+    let found = headings.iter().position(|(_, h)| h.text == heading);
+    let index = match found {
+        Some(index) => index,
+        None => {
+            let rest = markdown.trim_end_matches('\n');
+            let separator = if rest.is_empty() { "" } else { "\n\n" };
+            return format!("{}{}## {}\n\n{}\n", rest, separator, heading, content);
+        }
+    };
+    let (ref range, ref found) = headings[index];
+    let section_end = headings[index + 1..].iter()
+        .find(|&(_, h)| h.level <= found.level)
+        .map_or(markdown.len(), |(range, _)| range.start);
+
+    let mut output = String::from(&markdown[..range.end]);
+    if !output.ends_with('\n') {
+        output.push('\n');
+    }
+    let body = markdown[range.end..section_end].trim_matches('\n');
+    let parts = if append { [body, content] } else { [content, body] };
+    let body: Vec<&str> = parts.iter().cloned().filter(|part| !part.is_empty()).collect();
+    output.push('\n');
+    output.push_str(body.join("\n\n").as_str());
+    output.push('\n');
+    if section_end < markdown.len() {
+        output.push('\n');
+        output.push_str(&markdown[section_end..]);
+    }
+    output
+}
+
+/// Counts the whitespace separated words of the markdown, the contents of
+/// fenced code blocks are only counted if `include_code` is set. The fences
+/// themselves are never counted
+pub fn word_count(markdown: &str, include_code: bool) -> usize {
+    let mut count = 0;
+    let mut in_fence = false;
+    for line in markdown.lines() {
+        if is_fence(line) {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence && !include_code {
+            continue;
+        }
+        count += line.split_whitespace().count();
+    }
+    count
+}
+
+/// Converts the text into an anchor id, i.e. `Hello, World!` becomes
+/// `hello-world`
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    String::from(slug.trim_matches('-'))
+}
+
+/// Creates unique anchor ids for the given headings, repeated headings get
+/// a numeric suffix, i.e. `usage`, `usage-1`, `usage-2`. Headings without
+/// any letters or digits, like `!!!`, use `section` as their id
+pub fn unique_slugs(headings: &[Heading]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    headings.iter().map(|heading| {
+        let slug = match slugify(heading.text.as_str()) {
+            ref slug if slug.is_empty() => String::from("section"),
+            slug => slug
+        };
+        let mut candidate = slug.clone();
+        let mut i = 1;
+        while seen.contains(&candidate) {
+            candidate = format!("{}-{}", slug, i);
+            i += 1;
+        }
+        seen.insert(candidate.clone());
+        candidate
+    }).collect()
+}
+
+/// A `[[wikilink]]` found in a markdown document
+#[derive(Clone, Debug, PartialEq)]
+pub struct WikiLink {
+    /// the url of the page that is linked to, always starting with a slash
+    pub target: String,
+    /// the text of the link, the target itself if no label was given
+    pub label: String,
+}
+
+impl WikiLink {
+    /// Parses the inner part of a wikilink, i.e. `some/page|Label`
+    fn parse(inner: &str) -> Option<WikiLink> {
+        let (target, label) = match inner.find('|') {
+            Some(i) => (inner[..i].trim(), inner[i + 1..].trim()),
+            None => (inner.trim(), inner.trim())
+        };
+        if target.is_empty() {
+            return None;
+        }
+        let label = if label.is_empty() { target } else { label };
+        let target = if target.starts_with('/') {
+            String::from(target)
+        } else {
+            format!("/{}", target)
+        };
+        Some(WikiLink { target, label: String::from(label) })
+    }
+
+    /// The target of the link as it is used in a markdown link
+    pub fn href(&self) -> String {
+        self.target.replace(' ', "%20")
+    }
+}
+
+/// Calls `replace` for every wikilink outside of code along with the
+/// wikilink as written and puts its result in place of the wikilink
+fn replace_wikilinks<F>(markdown: &str, mut replace: F) -> String
+    where F: FnMut(&WikiLink, &str) -> String {
+    let mut output = String::with_capacity(markdown.len());
+    let mut in_fence = false;
+
+    for line in markdown.split_inclusive('\n') {
+        if is_fence(line) {
+            in_fence = !in_fence;
+        }
+        // `[[toc]]` is no link to a page
+        if in_fence || is_fence(line) || is_toc_marker(line) {
+            output.push_str(line);
+            continue;
+        }
+
+        let mut rest = line;
+        while !rest.is_empty() {
+            if rest.starts_with('`') {
+                // copy inline code verbatim up to the closing backticks
+                let ticks = rest.len() - rest.trim_start_matches('`').len();
+                let fence = &rest[..ticks];
+                let end = match rest[ticks..].find(fence) {
+                    Some(i) => ticks + i + ticks,
+                    None => ticks
+                };
+                output.push_str(&rest[..end]);
+                rest = &rest[end..];
+            } else if rest.starts_with("[[") {
+                let link = rest[2..].find("]]")
+                    .and_then(|end| WikiLink::parse(&rest[2..2 + end]).map(|l| (l, end)));
+                match link {
+                    Some((link, end)) => {
+                        output.push_str(replace(&link, &rest[..2 + end + 2]).as_str());
+                        rest = &rest[2 + end + 2..];
+                    }
+                    None => {
+                        output.push_str("[[");
+                        rest = &rest[2..];
+                    }
+                }
+            } else {
+                let first = rest.chars().next().map_or(1, |c| c.len_utf8());
+                let next = rest[first..].find(['`', '['])
+                    .map(|i| i + first)
+                    .unwrap_or(rest.len());
+                output.push_str(&rest[..next]);
+                rest = &rest[next..];
+            }
+        }
+    }
+    output
+}
+
+/// Returns all wikilinks outside of code in the markdown
+pub fn wikilinks(markdown: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    replace_wikilinks(markdown, |link, _| {
+        links.push(link.clone());
+        String::new()
+    });
+    links
+}
+
+/// Rewrites all wikilinks outside of code into standard markdown links
+pub fn expand_wikilinks(markdown: &str) -> String {
+    replace_wikilinks(markdown, |link, _| {
+        format!("[{}]({})", link.label, link.href())
+    })
+}
+
+/// Replaces everything inside of fenced code blocks and inline code with
+/// spaces, keeping the byte offsets of everything else intact
+fn mask_code(markdown: &str) -> String {
+    fn blank(s: &str) -> String {
+        let mut blanked = String::with_capacity(s.len());
+        for c in s.chars() {
+            if c == '\n' {
+                blanked.push('\n');
+            } else {
+                blanked.push_str(" ".repeat(c.len_utf8()).as_str());
+            }
+        }
+        blanked
+    }
+
+    let mut output = String::with_capacity(markdown.len());
+    let mut in_fence = false;
+    for line in markdown.split_inclusive('\n') {
+        if is_fence(line) {
+            in_fence = !in_fence;
+            output.push_str(blank(line).as_str());
+            continue;
+        }
+        if in_fence {
+            output.push_str(blank(line).as_str());
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find('`') {
+            output.push_str(&rest[..start]);
+            let code = &rest[start..];
+            let ticks = code.len() - code.trim_start_matches('`').len();
+            let end = match code[ticks..].find(&code[..ticks]) {
+                Some(i) => ticks + i + ticks,
+                None => ticks
+            };
+            output.push_str(blank(&code[..end]).as_str());
+            rest = &code[end..];
+        }
+        output.push_str(rest);
+    }
+    output
+}
+
+/// Replaces the known emoji shortcodes like `:smile:` outside of code with
+/// the emoji, unknown shortcodes are left untouched
+pub fn expand_emoji(markdown: &str) -> String {
+    let masked = mask_code(markdown);
+    let mut output = String::with_capacity(markdown.len());
+    let mut copied = 0;
+    let mut search = 0;
+    while let Some(i) = masked[search..].find(':') {
+        let start = search + i;
+        search = start + 1;
+        // i.e. `12:30:00` or `http://`
+        if masked[..start].chars().next_back().is_some_and(char::is_alphanumeric) {
+            continue;
+        }
+        let name_len = masked[start + 1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-'))
+            .unwrap_or(masked.len() - start - 1);
+        let end = start + 1 + name_len;
+        if name_len == 0 || !masked[end..].starts_with(':') {
+            continue;
+        }
+        if let Some(emoji) = emoji::lookup(&masked[start + 1..end]) {
+            output.push_str(&markdown[copied..start]);
+            output.push_str(emoji);
+            copied = end + 1;
+            search = end + 1;
+        }
+    }
+    output.push_str(&markdown[copied..]);
+    output
+}
+
+/// Returns the first paragraph of the markdown that is not part of a
+/// heading, a code block or an HTML block, with its lines joined by spaces
+pub fn first_paragraph(markdown: &str) -> Option<String> {
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+
+    for line in markdown.lines() {
+        let ends_paragraph = is_fence(line) || line.trim().is_empty()
+            || parse_atx_heading(line).is_some();
+        if !in_fence && ends_paragraph && !paragraph.is_empty() {
+            break;
+        }
+        if is_fence(line) {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence || ends_paragraph {
+            continue;
+        }
+        if paragraph.is_empty() {
+            if line.starts_with("    ") || line.trim_start().starts_with('<')
+                || setext_level(line).is_some() {
+                continue;
+            }
+        } else if setext_level(line).is_some() {
+            // the lines so far have been a setext heading
+            paragraph.clear();
+            continue;
+        }
+        paragraph.push(line.trim());
+    }
+
+    if paragraph.is_empty() {
+        None
+    } else {
+        Some(paragraph.join(" "))
+    }
+}
+
+/// Parses an inline link or image starting with the opening bracket,
+/// returns the text of the link and the offset after the link
+fn parse_inline_link(text: &str) -> Option<(&str, usize)> {
+    let close = closing_bracket(text)?;
+    if !text[close + 1..].starts_with('(') {
+        return None;
+    }
+    let (_, offset) = parse_destination(&text[close + 2..])?;
+    Some((&text[1..close], close + 2 + offset))
+}
+
+/// Returns the offset of the bracket closing the one the text starts with
+fn closing_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => ()
+        }
+    }
+    None
+}
+
+/// Parses a full or collapsed reference link like `[text][label]` starting
+/// with the opening bracket, returns the text and the offset after the link
+fn parse_reference_link(text: &str) -> Option<(&str, usize)> {
+    let close = closing_bracket(text)?;
+    let label = text[close + 1..].strip_prefix('[')?;
+    let end = label.find(']')?;
+    if label[..end].contains('[') {
+        return None;
+    }
+    Some((&text[1..close], close + 2 + end + 1))
+}
+
+/// Removes the inline markdown syntax from the text, leaving only the text
+/// of links, images, code and emphasis
+fn strip_inline_into(text: &str, output: &mut String) {
+    let mut rest = text;
+    let mut previous = None;
+    while let Some(c) = rest.chars().next() {
+        let len = c.len_utf8();
+        let next = rest[len..].chars().next();
+        match c {
+            '\\' if next.is_some() => {
+                let next = next.unwrap();
+                output.push(next);
+                previous = Some(next);
+                rest = &rest[len + next.len_utf8()..];
+                continue;
+            }
+            '`' => {
+                let ticks = rest.len() - rest.trim_start_matches('`').len();
+                if let Some(end) = rest[ticks..].find(&rest[..ticks]) {
+                    output.push_str(&rest[ticks..ticks + end]);
+                    rest = &rest[ticks + end + ticks..];
+                } else {
+                    rest = &rest[ticks..];
+                }
+                previous = Some('`');
+                continue;
+            }
+            '!' | '[' => {
+                let start = if c == '!' { 1 } else { 0 };
+                if rest[start..].starts_with('[') {
+                    let link = parse_inline_link(&rest[start..])
+                        .or_else(|| parse_reference_link(&rest[start..]));
+                    if let Some((inner, end)) = link {
+                        strip_inline_into(inner, output);
+                        rest = &rest[start + end..];
+                        previous = Some(')');
+                        continue;
+                    }
+                }
+                output.push(c);
+            }
+            '*' | '~' => (),
+            '_' => {
+                let inside_word = previous.is_some_and(char::is_alphanumeric)
+                    && next.is_some_and(char::is_alphanumeric);
+                if inside_word {
+                    output.push(c);
+                }
+            }
+            c => output.push(c)
+        }
+        previous = Some(c);
+        rest = &rest[len..];
+    }
+}
+
+/// Converts inline markdown to plain text, i.e. `see [the *docs*](/docs)`
+/// becomes `see the docs`. Whitespace is collapsed to single spaces
+pub fn strip_inline(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    strip_inline_into(expand_wikilinks(text).as_str(), &mut output);
+    output.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Converts the markdown to readable plain text, i.e. for indexing it. The
+/// markers of headings, lists, quotes and code fences are removed along
+/// with link reference definitions and the inline syntax, see
+/// `strip_inline`. Blocks are separated by a single empty line
+pub fn plain_text(markdown: &str) -> String {
+    let mut blocks: Vec<Vec<String>> = vec![Vec::new()];
+    let mut in_fence = false;
+
+    for line in markdown.lines() {
+        if is_fence(line) {
+            in_fence = !in_fence;
+            blocks.push(Vec::new());
+            continue;
+        }
+        if in_fence {
+            blocks.last_mut().unwrap().push(String::from(line.trim_end()));
+            continue;
+        }
+        if line.trim().is_empty() || parse_definition(line).is_some() {
+            blocks.push(Vec::new());
+            continue;
+        }
+        if let Some(heading) = parse_atx_heading(line) {
+            blocks.push(vec![strip_inline(heading.text.as_str())]);
+            blocks.push(Vec::new());
+            continue;
+        }
+        if setext_level(line).is_some() || is_table_delimiter(line) {
+            // underlines of headings and thematic breaks
+            blocks.push(Vec::new());
+            continue;
+        }
+        let text = strip_block_markers(line);
+        let text = match text.strip_prefix('|') {
+            Some(row) => row.trim_end_matches('|').split('|')
+                .map(strip_inline)
+                .collect::<Vec<String>>()
+                .join(" "),
+            None => strip_inline(text)
+        };
+        if !text.is_empty() {
+            blocks.last_mut().unwrap().push(text);
+        }
+    }
+
+    blocks.iter()
+        .filter(|block| !block.is_empty())
+        .map(|block| block.join("\n"))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Returns whether the line is the delimiter row of a table like `|---|:-:|`
+fn is_table_delimiter(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with('|') && line.contains('-')
+        && line.chars().all(|c| c == '|' || c == '-' || c == ':' || c == ' ')
+}
+
+/// Removes the markers of quotes and list items from the start of the line
+fn strip_block_markers(line: &str) -> &str {
+    let mut line = line.trim();
+    loop {
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let stripped = if let Some(rest) = line.strip_prefix('>') {
+            rest
+        } else if line.starts_with(['-', '*', '+']) && line[1..].starts_with(' ') {
+            &line[2..]
+        } else if digits > 0 && line[digits..].starts_with(['.', ')'])
+            && line[digits + 1..].starts_with(' ') {
+            &line[digits + 2..]
+        } else {
+            return line;
+        };
+        line = stripped.trim_start();
+    }
+}
+
+/// An inline link or image like `[text](target "title")`
+#[derive(Clone, Debug, PartialEq)]
+pub struct InlineLink {
+    /// the text of the link or the alt text of the image
+    pub text: String,
+    /// the target of the link or the source of the image
+    pub target: String,
+    /// whether this is an image rather than a link
+    pub image: bool,
+}
+
+/// Parses the destination of an inline link starting after the opening
+/// parenthesis, returns the range of the destination and the offset after
+/// the link
+fn parse_destination(text: &str) -> Option<(Range<usize>, usize)> {
+    let start = text.len() - text.trim_start().len();
+    let rest = &text[start..];
+    let (destination, mut offset) = if let Some(inner) = rest.strip_prefix('<') {
+        let end = inner.find('>')?;
+        (start + 1..start + 1 + end, start + 1 + end + 1)
+    } else {
+        let mut depth = 0;
+        let mut end = rest.len();
+        for (i, c) in rest.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => { end = i; break; }
+                ')' => depth -= 1,
+                c if c.is_whitespace() => { end = i; break; }
+                _ => ()
+            }
+        }
+        (start..start + end, start + end)
+    };
+    // skip an optional title up to the closing parenthesis
+    let close = text[offset..].find(')')?;
+    offset += close + 1;
+    Some((destination, offset))
+}
+
+/// Finds all inline links and images in the markdown with its code masked,
+/// returns the offsets of their brackets and the range of their destination
+fn find_inline_links(masked: &str) -> Vec<(usize, usize, Range<usize>)> {
+    let bytes = masked.as_bytes();
+    let mut links = Vec::new();
+
+    for (open, _) in masked.match_indices('[') {
+        if open > 0 && bytes[open - 1] == b'\\' {
+            continue;
+        }
+        let mut depth = 0;
+        let mut close = None;
+        for (i, c) in masked[open..].char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(open + i);
+                        break;
+                    }
+                }
+                _ => ()
+            }
+        }
+        let close = match close {
+            Some(close) => close,
+            None => continue
+        };
+        if !masked[close + 1..].starts_with('(') {
+            continue;
+        }
+        if let Some((destination, _)) = parse_destination(&masked[close + 2..]) {
+            let start = close + 2;
+            links.push((open, close, start + destination.start..start + destination.end));
+        }
+    }
+    links
+}
+
+/// Returns all inline links and images outside of code in the markdown
+pub fn inline_links(markdown: &str) -> Vec<InlineLink> {
+    let masked = mask_code(markdown);
+    let bytes = masked.as_bytes();
+    find_inline_links(masked.as_str()).into_iter().map(|(open, close, destination)| {
+        InlineLink {
+            text: String::from(&markdown[open + 1..close]),
+            target: String::from(&markdown[destination]),
+            image: open > 0 && bytes[open - 1] == b'!',
+        }
+    }).collect()
+}
+
+/// Normalizes the label of a reference link, labels are matched ignoring
+/// case and whitespace
+fn normalize_label(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+}
+
+/// Parses a link reference definition like `[label]: target "title"`,
+/// returns the label and the target
+fn parse_definition(line: &str) -> Option<(String, String)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = line.trim_start().strip_prefix('[')?;
+    let close = rest.find("]:")?;
+    let label = &rest[..close];
+    let destination = rest[close + 2..].trim();
+    if label.trim().is_empty() || destination.is_empty() {
+        return None;
+    }
+    let target = match destination.strip_prefix('<') {
+        Some(inner) => &inner[..inner.find('>')?],
+        None => destination.split_whitespace().next()?
+    };
+    Some((normalize_label(label), String::from(target)))
+}
+
+/// Returns the targets of all link reference definitions outside of code in
+/// the markdown by their normalized label, the first definition wins
+fn reference_definitions(markdown: &str) -> HashMap<String, String> {
+    let mut definitions = HashMap::new();
+    let mut in_fence = false;
+    for line in markdown.lines() {
+        if is_fence(line) {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some((label, target)) = parse_definition(line) {
+            definitions.entry(label).or_insert(target);
+        }
+    }
+    definitions
+}
+
+/// Returns all reference links and images outside of code in the markdown
+/// which have a matching definition, i.e. `[text][label]`, `[label][]` or
+/// `[label]`, with their targets taken from the definition
+pub fn reference_links(markdown: &str) -> Vec<InlineLink> {
+    let definitions = reference_definitions(markdown);
+    if definitions.is_empty() {
+        return Vec::new();
+    }
+    let masked = mask_code(markdown);
+    let bytes = masked.as_bytes();
+    let mut links = Vec::new();
+    let mut skip_until = 0;
+
+    for (open, _) in masked.match_indices('[') {
+        if open < skip_until {
+            continue;
+        }
+        let previous = if open > 0 { bytes[open - 1] } else { b'\n' };
+        if previous == b'\\' || previous == b'[' || masked[open + 1..].starts_with('[') {
+            continue;
+        }
+        let close = match masked[open + 1..].find(']') {
+            Some(close) => open + 1 + close,
+            None => continue
+        };
+        let text = &markdown[open + 1..close];
+        let after = &masked[close + 1..];
+        let line_start = masked[..open].rfind('\n').map_or(0, |i| i + 1);
+        let is_definition = after.starts_with(':') && masked[line_start..open].trim().is_empty();
+        let (label, end) = if after.starts_with('(') || is_definition {
+            continue;
+        } else if let Some(rest) = after.strip_prefix('[') {
+            match rest.find(']') {
+                Some(0) => (text, close + 3),
+                Some(i) => (&markdown[close + 2..close + 2 + i], close + 2 + i + 1),
+                None => continue
+            }
+        } else {
+            (text, close + 1)
+        };
+        if let Some(target) = definitions.get(&normalize_label(label)) {
+            links.push(InlineLink {
+                text: String::from(text),
+                target: target.clone(),
+                image: previous == b'!',
+            });
+            skip_until = end;
+        }
+    }
+    links
+}
+
+/// Replaces the targets of all links outside of code in the markdown with
+/// the result of `rewrite`, covering inline links, link reference
+/// definitions and wikilinks. Targets for which it returns `None` are left
+/// untouched, as are the sources of images. Returns the rewritten markdown
+/// and the number of links that have been changed
+pub fn rewrite_link_targets<F>(markdown: &str, rewrite: F) -> (String, usize)
+    where F: Fn(&str) -> Option<String> {
+    let masked = mask_code(markdown);
+    let bytes = masked.as_bytes();
+    let mut destinations: Vec<Range<usize>> = find_inline_links(masked.as_str()).into_iter()
+        .filter(|&(open, _, _)| open == 0 || bytes[open - 1] != b'!')
+        .map(|(_, _, destination)| destination)
+        .collect();
+
+    let mut line_start = 0;
+    for line in masked.split_inclusive('\n') {
+        if let Some((_, target)) = parse_definition(line) {
+            let after = line.find("]:").unwrap() + 2;
+            if let Some(i) = line[after..].find(target.as_str()) {
+                let start = line_start + after + i;
+                destinations.push(start..start + target.len());
+            }
+        }
+        line_start += line.len();
+    }
+    destinations.sort_by_key(|destination| destination.start);
+
+    let mut count = 0;
+    let mut output = String::with_capacity(markdown.len());
+    let mut copied = 0;
+    for destination in destinations {
+        if let Some(target) = rewrite(&markdown[destination.clone()]) {
+            output.push_str(&markdown[copied..destination.start]);
+            output.push_str(target.as_str());
+            copied = destination.end;
+            count += 1;
+        }
+    }
+    output.push_str(&markdown[copied..]);
+
+    let output = replace_wikilinks(output.as_str(), |link, written| {
+        let target = match rewrite(link.target.as_str()) {
+            Some(target) => target,
+            None => return String::from(written)
+        };
+        count += 1;
+        let inner = &written[2..written.len() - 2];
+        match inner.find('|') {
+            Some(_) => format!("[[{}|{}]]", target, link.label),
+            None if inner.trim().starts_with('/') => format!("[[{}]]", target),
+            None => format!("[[{}]]", target.trim_start_matches('/'))
+        }
+    });
+    (output, count)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Heading, InlineLink, WikiLink, expand_emoji, expand_wikilinks, first_paragraph,
+                has_toc_marker, headings, insert_section, inline_links, plain_text, reference_links,
+                rewrite_link_targets, slugify, strip_inline, unique_slugs, wikilinks, word_count};
+
+    fn heading(level: u8, text: &str) -> Heading {
+        Heading { level, text: String::from(text) }
+    }
+
+    #[test]
+    fn test_headings_atx() {
+        assert_eq!(
+            headings("# One\ntext\n## Two ##\n###no heading\n####### too deep"),
+            vec![heading(1, "One"), heading(2, "Two")]
+        )
+    }
+
+    #[test]
+    fn test_headings_setext() {
+        assert_eq!(
+            headings("One\n===\n\nTwo\n---\n\ntext\n\n---\n"),
+            vec![heading(1, "One"), heading(2, "Two")]
+        )
+    }
+
+    #[test]
+    fn test_headings_skip_code() {
+        assert_eq!(
+            headings("```\n# not a heading\n```\n# Heading"),
+            vec![heading(1, "Heading")]
+        )
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Installing -- the crate  "), "installing-the-crate");
+        assert_eq!(slugify("snake_case"), "snake-case");
+    }
+
+    #[test]
+    fn test_unique_slugs() {
+        let headings = vec![heading(1, "!!!"), heading(2, "\u{1F389}"), heading(2, "Section"),
+                            heading(1, "Usage"), heading(2, "Usage")];
+        assert_eq!(unique_slugs(&headings), vec!["section", "section-1", "section-2", "usage", "usage-1"]);
+    }
+
+    #[test]
+    fn test_expand_wikilinks() {
+        assert_eq!(
+            expand_wikilinks("see [[some/page]] and [[/other|the other]]"),
+            "see [some/page](/some/page) and [the other](/other)"
+        );
+        assert_eq!(
+            expand_wikilinks("[[Other Page]]"),
+            "[Other Page](/Other%20Page)"
+        );
+        assert_eq!(expand_wikilinks("[[]] [[ unclosed"), "[[]] [[ unclosed");
+        assert_eq!(expand_wikilinks("é [[a|]] ü"), "é [a](/a) ü");
+    }
+
+    #[test]
+    fn test_expand_wikilinks_skip_code() {
+        assert_eq!(
+            expand_wikilinks("`[[code]]` ``a [[b]]`` [[c]]\n```\n[[fenced]]\n```\n"),
+            "`[[code]]` ``a [[b]]`` [c](/c)\n```\n[[fenced]]\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_wikilinks() {
+        assert_eq!(
+            wikilinks("[[a]] [[b|B]]"),
+            vec![
+                WikiLink { target: String::from("/a"), label: String::from("a") },
+                WikiLink { target: String::from("/b"), label: String::from("B") },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_links() {
+        let link = |text: &str, target: &str, image: bool| InlineLink {
+            text: String::from(text),
+            target: String::from(target),
+            image,
+        };
+        assert_eq!(
+            inline_links("[a](/a) ![img](i.png \"title\") [b](<../b c>) \
+                          [![nested](n.png)](/n) [not a link] `[code](/c)`"),
+            vec![
+                link("a", "/a", false),
+                link("img", "i.png", true),
+                link("b", "../b c", false),
+                link("![nested](n.png)", "/n", false),
+                link("nested", "n.png", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_word_count() {
+        let markdown = "# Title\n\nsome  words\there\n```rust\nlet x = 1;\n```\nend";
+        assert_eq!(word_count(markdown, true), 10);
+        assert_eq!(word_count(markdown, false), 6);
+        assert_eq!(word_count("", true), 0);
+    }
+
+    #[test]
+    fn test_first_paragraph() {
+        let markdown = "# Title\n\nSubtitle\n--------\n\n```\ncode\n```\n\
+                        First line\nsecond line\n\nSecond paragraph";
+        assert_eq!(first_paragraph(markdown), Some(String::from("First line second line")));
+        assert_eq!(first_paragraph("# Only a heading"), None);
+    }
+
+    #[test]
+    fn test_strip_inline() {
+        assert_eq!(
+            strip_inline("see [the *docs*](/docs \"Docs\") and ![a logo](logo.png)"),
+            "see the docs and a logo"
+        );
+        assert_eq!(
+            strip_inline("**bold**, _em_, ~~gone~~, `snake_case` and [[some page|a page]]"),
+            "bold, em, gone, snake_case and a page"
+        );
+        assert_eq!(strip_inline("an \\*escaped\\* star"), "an *escaped* star");
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let markdown = "# The *Title* #\n\nSee [the docs](/docs \"Docs\") and [more][ref].\n\
+                        Some **bold** and _em_ text\n\n\
+                        > - quoted [[wiki page|item]]\n1. first\n\n\
+                        Setext\n------\n\n```rust\nlet x = 1;\n```\n\n\
+                        | a | `b` |\n|---|:-:|\n\n[ref]: https://example.com/more\n";
+        assert_eq!(
+            plain_text(markdown),
+            "The Title\n\nSee the docs and more.\nSome bold and em text\n\n\
+             quoted item\nfirst\n\nSetext\n\nlet x = 1;\n\na b"
+        );
+        let text = plain_text(markdown);
+        for syntax in &["#", "*", "[", "](", "/docs", "example.com", "```", "|"] {
+            assert!(!text.contains(syntax), "{} was not removed", syntax);
+        }
+        assert_eq!(plain_text(""), "");
+    }
+
+    #[test]
+    fn test_reference_links() {
+        let markdown = "[full][Ref One] and [one ref][] and [shortcut] ![logo][img] \
+                        [undefined][nope] [inline](/x) [[wiki]] `[shortcut]`\n\n\
+                        [ref one]: /one \"Title\"\n[one ref]: <two>\n\
+                        [Shortcut]: https://example.com\n   [img]: logo.png\n";
+        assert_eq!(reference_links(markdown), vec![
+            InlineLink { text: String::from("full"), target: String::from("/one"), image: false },
+            InlineLink { text: String::from("one ref"), target: String::from("two"), image: false },
+            InlineLink {
+                text: String::from("shortcut"),
+                target: String::from("https://example.com"),
+                image: false,
+            },
+            InlineLink { text: String::from("logo"), target: String::from("logo.png"), image: true },
+        ]);
+    }
+
+    #[test]
+    fn test_rewrite_link_targets() {
+        let markdown = "[a](old) ![img](old) [b](<old> \"Title\") `[c](old)` [d](other)\n\
+                        [[old]] [[/old|Label]] [[other]]\n\n[ref]: old \"Ref\"\n";
+        let (rewritten, count) = rewrite_link_targets(markdown, |target| {
+            if target == "old" || target == "/old" { Some(String::from("/new")) } else { None }
+        });
+        assert_eq!(count, 5);
+        assert_eq!(
+            rewritten,
+            "[a](/new) ![img](old) [b](</new> \"Title\") `[c](old)` [d](other)\n\
+             [[new]] [[/new|Label]] [[other]]\n\n[ref]: /new \"Ref\"\n"
+        );
+        assert_eq!(rewrite_link_targets("[a](b)", |_| None), (String::from("[a](b)"), 0));
+    }
+
+    #[test]
+    fn test_expand_emoji() {
+        assert_eq!(expand_emoji("Done :tada: :+1::smile:"), "Done \u{1F389} \u{1F44D}\u{1F604}");
+        assert_eq!(expand_emoji(":unknown: at 12:30:00, see http://x:smile:"),
+                   ":unknown: at 12:30:00, see http://x:smile:");
+        assert_eq!(expand_emoji("`:smile:` and :smile:\n```\n:smile:\n```\n"),
+                   "`:smile:` and \u{1F604}\n```\n:smile:\n```\n");
+        assert_eq!(expand_emoji("a :: b :smile"), "a :: b :smile");
+    }
+
+    #[test]
+    fn test_toc_marker() {
+        assert!(has_toc_marker("# Title\n\n  [TOC]  \n\ntext"));
+        assert!(has_toc_marker("[[toc]]"));
+        assert!(!has_toc_marker("see [TOC] inline\n```\n[TOC]\n```\n"));
+        assert_eq!(wikilinks("[[toc]]\n[[page]]").len(), 1);
+    }
+
+    #[test]
+    fn test_insert_section() {
+        let markdown = "# Changelog\n\n## 1.1\n\n- fix\n\n## 1.0\n\n- initial\n";
+        assert_eq!(insert_section(markdown, "1.1", "- feature", true),
+                   "# Changelog\n\n## 1.1\n\n- fix\n\n- feature\n\n## 1.0\n\n- initial\n");
+        assert_eq!(insert_section(markdown, "1.0", "- first", false),
+                   "# Changelog\n\n## 1.1\n\n- fix\n\n## 1.0\n\n- first\n\n- initial\n");
+        // the section of a heading includes the deeper headings below it
+        assert_eq!(insert_section(markdown, "Changelog", "end", true),
+                   "# Changelog\n\n## 1.1\n\n- fix\n\n## 1.0\n\n- initial\n\nend\n");
+        assert_eq!(insert_section("Intro\n=====", "Intro", "text\n", true), "Intro\n=====\n\ntext\n");
+        assert_eq!(insert_section("text\n```\n# Notes\n```\n", "Notes", "a", true),
+                   "text\n```\n# Notes\n```\n\n## Notes\n\na\n");
+        assert_eq!(insert_section("", "Notes", "a", false), "## Notes\n\na\n");
+    }
+}
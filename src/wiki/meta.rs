@@ -0,0 +1,305 @@
+use frontmatter;
+use toml;
+use yaml_rust::YamlEmitter;
+use yaml_rust::yaml::{self, Yaml};
+
+use super::WikiError;
+
+/// The formats the frontmatter of a page can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FrontmatterFormat {
+    /// YAML delimited by `---`
+    #[default]
+    Yaml,
+    /// TOML delimited by `+++`
+    Toml,
+}
+
+impl FrontmatterFormat {
+    /// Returns the line delimiting frontmatter in this format
+    pub fn delimiter(&self) -> &'static str {
+        match *self {
+            FrontmatterFormat::Yaml => "---",
+            FrontmatterFormat::Toml => "+++",
+        }
+    }
+}
+
+/// The frontmatter of a page as it has been read from or written to its
+/// file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frontmatter {
+    /// the parsed frontmatter, TOML is converted to YAML
+    pub meta: Yaml,
+    /// the format the frontmatter is written in
+    pub format: FrontmatterFormat,
+    /// the frontmatter exactly as written in the file, including the
+    /// delimiters
+    pub text: String,
+}
+
+/// Splits the raw contents of a page into the frontmatter and the markdown
+/// following it. TOML frontmatter is converted to YAML, so pages can treat
+/// both the same way
+/// # Errors
+/// This will return `WikiError::Frontmatter` if the frontmatter can not be
+/// parsed
+pub fn parse(raw: &str) -> Result<(Option<Frontmatter>, &str), WikiError> {
+    let (meta, format, markdown) = match split_toml(raw) {
+        Some((body, markdown)) => {
+            let table = body.parse::<toml::Table>()
+                .map_err(|e| WikiError::Frontmatter(e.to_string()))?;
+            (Some(toml_to_yaml(toml::Value::Table(table))), FrontmatterFormat::Toml, markdown)
+        }
+        None => match frontmatter::parse_and_find_content(raw) {
+            Ok((meta, markdown)) => (meta, FrontmatterFormat::Yaml, markdown),
+            Err(e) => return Err(WikiError::Frontmatter(e.to_string()))
+        }
+    };
+    let frontmatter = meta.map(|meta| Frontmatter {
+        meta,
+        format,
+        text: String::from(&raw[..raw.len() - markdown.len()]),
+    });
+    Ok((frontmatter, markdown))
+}
+
+/// Splits the raw contents into the body of TOML frontmatter and the
+/// markdown following it, if they start with a `+++` line that is closed by
+/// another one. The delimiters have to be lines of their own, which may end
+/// with `\r\n`, and the closing one may also end the file
+fn split_toml(raw: &str) -> Option<(&str, &str)> {
+    let is_delimiter = |line: &str| line.trim_end_matches(['\r', '\n']) == "+++";
+    let mut lines = raw.split_inclusive('\n');
+    let first = lines.next()?;
+    if !first.ends_with('\n') || !is_delimiter(first) {
+        return None;
+    }
+    let mut start = first.len();
+    for line in lines {
+        if is_delimiter(line) {
+            return Some((&raw[first.len()..start], &raw[start + line.len()..]));
+        }
+        start += line.len();
+    }
+    None
+}
+
+/// Serializes the frontmatter in the given format, including the
+/// delimiters. Only mappings can be written as TOML, other frontmatter is
+/// always written as YAML
+pub fn emit(meta: &Yaml, format: FrontmatterFormat) -> String {
+    if format == FrontmatterFormat::Toml {
+        if let Some(toml::Value::Table(table)) = yaml_to_toml(meta) {
+            let body = toml::to_string(&table).expect("tables can always be serialized");
+            return wrap(body.trim_matches('\n'), format);
+        }
+    }
+    let mut body = String::new();
+    {
+        let mut emitter = YamlEmitter::new(&mut body);
+        emitter.dump(meta).unwrap();
+    }
+    // the emitter starts the document with its own `---` and does not end it
+    // with a newline
+    wrap(body.trim_start_matches("---").trim_matches('\n'), FrontmatterFormat::Yaml)
+}
+
+/// Serializes the frontmatter as JSON. Mappings become objects with their
+/// keys converted to strings, reals that JSON can not represent and values
+/// that are no valid YAML become `null`
+pub fn to_json(meta: &Yaml) -> String {
+    let mut json = String::new();
+    write_json(meta, &mut json);
+    json
+}
+
+/// Appends the JSON representation of the value to the output
+fn write_json(value: &Yaml, output: &mut String) {
+    match *value {
+        Yaml::String(ref s) => write_json_string(s, output),
+        Yaml::Integer(i) => output.push_str(i.to_string().as_str()),
+        Yaml::Real(_) => match value.as_f64() {
+            Some(f) if f.is_finite() => output.push_str(format!("{:?}", f).as_str()),
+            _ => output.push_str("null")
+        },
+        Yaml::Boolean(b) => output.push_str(if b { "true" } else { "false" }),
+        Yaml::Array(ref array) => {
+            output.push('[');
+            for (i, item) in array.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                write_json(item, output);
+            }
+            output.push(']');
+        }
+        Yaml::Hash(ref hash) => {
+            output.push('{');
+            for (i, (key, value)) in hash.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                let key = match *key {
+                    Yaml::String(ref s) | Yaml::Real(ref s) => s.clone(),
+                    Yaml::Integer(i) => i.to_string(),
+                    Yaml::Boolean(b) => b.to_string(),
+                    _ => String::from("null")
+                };
+                write_json_string(key.as_str(), output);
+                output.push(':');
+                write_json(value, output);
+            }
+            output.push('}');
+        }
+        _ => output.push_str("null")
+    }
+}
+
+/// Appends the text as a quoted and escaped JSON string to the output
+fn write_json_string(text: &str, output: &mut String) {
+    output.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(format!("\\u{:04x}", c as u32).as_str()),
+            c => output.push(c)
+        }
+    }
+    output.push('"');
+}
+
+/// Encloses the serialized frontmatter in the delimiters of the format
+fn wrap(body: &str, format: FrontmatterFormat) -> String {
+    let mut buffer = String::new();
+    buffer.push_str(format.delimiter());
+    buffer.push('\n');
+    if !body.is_empty() {
+        buffer.push_str(body);
+        buffer.push('\n');
+    }
+    buffer.push_str(format.delimiter());
+    buffer.push('\n');
+    buffer
+}
+
+/// Converts a TOML value to the equivalent YAML, dates are kept as strings
+fn toml_to_yaml(value: toml::Value) -> Yaml {
+    match value {
+        toml::Value::String(s) => Yaml::String(s),
+        toml::Value::Integer(i) => Yaml::Integer(i),
+        toml::Value::Float(f) => Yaml::Real(format!("{:?}", f)),
+        toml::Value::Boolean(b) => Yaml::Boolean(b),
+        toml::Value::Datetime(d) => Yaml::String(d.to_string()),
+        toml::Value::Array(array) => Yaml::Array(array.into_iter().map(toml_to_yaml).collect()),
+        toml::Value::Table(table) => {
+            let mut hash = yaml::Hash::new();
+            for (key, value) in table {
+                hash.insert(Yaml::String(key), toml_to_yaml(value));
+            }
+            Yaml::Hash(hash)
+        }
+    }
+}
+
+/// Converts a YAML value to the equivalent TOML, which has no notion of
+/// null, so null values and keys that are no scalars are left out
+fn yaml_to_toml(value: &Yaml) -> Option<toml::Value> {
+    match *value {
+        Yaml::String(ref s) => Some(toml::Value::String(s.clone())),
+        Yaml::Integer(i) => Some(toml::Value::Integer(i)),
+        Yaml::Real(_) => value.as_f64().map(toml::Value::Float),
+        Yaml::Boolean(b) => Some(toml::Value::Boolean(b)),
+        Yaml::Array(ref array) => {
+            Some(toml::Value::Array(array.iter().filter_map(yaml_to_toml).collect()))
+        }
+        Yaml::Hash(ref hash) => {
+            let mut table = toml::Table::new();
+            for (key, value) in hash {
+                let key = match *key {
+                    Yaml::String(ref s) | Yaml::Real(ref s) => s.clone(),
+                    Yaml::Integer(i) => i.to_string(),
+                    Yaml::Boolean(b) => b.to_string(),
+                    _ => continue
+                };
+                if let Some(value) = yaml_to_toml(value) {
+                    table.insert(key, value);
+                }
+            }
+            Some(toml::Value::Table(table))
+        }
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yaml_rust::yaml::Yaml;
+    extern crate serde_json;
+    use super::{emit, parse, to_json, FrontmatterFormat};
+
+    #[test]
+    fn test_parse_toml() {
+        let raw = "+++\ntitle = \"Toml\"\ntags = [\"a\", \"b\"]\n\n[author]\nname = \"Jane\"\n\
+                   +++\n# Body";
+        let (frontmatter, markdown) = parse(raw).expect("parse frontmatter");
+        let frontmatter = frontmatter.expect("frontmatter");
+        assert_eq!(frontmatter.format, FrontmatterFormat::Toml);
+        assert_eq!(frontmatter.text, &raw[..raw.len() - 6]);
+        assert_eq!(markdown, "# Body");
+        let meta = frontmatter.meta;
+        assert_eq!(meta["title"].as_str(), Some("Toml"));
+        assert_eq!(meta["tags"][1].as_str(), Some("b"));
+        assert_eq!(meta["author"]["name"].as_str(), Some("Jane"));
+        assert!(parse("+++\ntitle = \n+++\n").is_err());
+        assert!(parse("+++ not frontmatter").unwrap().0.is_none());
+    }
+
+    #[test]
+    fn test_parse_toml_delimiters() {
+        let (frontmatter, markdown) = parse("+++\r\ntitle = \"Crlf\"\r\n+++\r\n# Body\r\n").unwrap();
+        let frontmatter = frontmatter.expect("frontmatter");
+        assert_eq!(frontmatter.format, FrontmatterFormat::Toml);
+        assert_eq!(frontmatter.meta["title"].as_str(), Some("Crlf"));
+        assert_eq!(frontmatter.text, "+++\r\ntitle = \"Crlf\"\r\n+++\r\n");
+        assert_eq!(markdown, "# Body\r\n");
+
+        let (frontmatter, markdown) = parse("+++\ntitle = \"Eof\"\n+++").unwrap();
+        assert_eq!(frontmatter.expect("frontmatter").meta["title"].as_str(), Some("Eof"));
+        assert_eq!(markdown, "");
+
+        // the closing delimiter has to be a line of its own
+        let (frontmatter, markdown) = parse("+++\nnote = \"\"\"\na+++\n\"\"\"\n+++\ntext").unwrap();
+        assert_eq!(frontmatter.expect("frontmatter").meta["note"].as_str(), Some("a+++\n"));
+        assert_eq!(markdown, "text");
+        assert!(parse("+++\ntitle = \"open\"\n").unwrap().0.is_none());
+    }
+
+    #[test]
+    fn test_emit() {
+        let meta = parse("---\ntitle: Both\nweight: 1.5\n---\n").unwrap().0.unwrap().meta;
+        assert_eq!(emit(&meta, FrontmatterFormat::Yaml), "---\ntitle: Both\nweight: 1.5\n---\n");
+        assert_eq!(emit(&meta, FrontmatterFormat::Toml), "+++\ntitle = \"Both\"\nweight = 1.5\n+++\n");
+        let list = Yaml::Array(vec![Yaml::Integer(1)]);
+        assert_eq!(emit(&list, FrontmatterFormat::Toml), "---\n- 1\n---\n");
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = |raw: &str| {
+            let meta = parse(raw).unwrap().0.unwrap().meta;
+            serde_json::from_str::<serde_json::Value>(to_json(&meta).as_str()).expect("valid json")
+        };
+        assert_eq!(json("---\ntitle: \"Say \\\"hi\\\"\"\ncount: 3\nratio: 0.5\ndraft: false\nnone: ~\n---\n"),
+                   serde_json::json!({"title": "Say \"hi\"", "count": 3, "ratio": 0.5,
+                                      "draft": false, "none": null}));
+        assert_eq!(json("---\nauthor:\n  name: Jane\n  links:\n    - a\n    - {b: 1}\n---\n"),
+                   serde_json::json!({"author": {"name": "Jane", "links": ["a", {"b": 1}]}}));
+        assert_eq!(json("---\n- 1\n- [two]\n- true\n---\n"), serde_json::json!([1, ["two"], true]));
+        assert_eq!(to_json(&Yaml::String(String::from("tab\tline\n\u{1}"))), "\"tab\\tline\\n\\u0001\"");
+    }
+}
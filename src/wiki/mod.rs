@@ -1,39 +1,563 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io;
 use std::io::Read;
 use std::io::Write;
-use std::path::Path;
+use std::ops::{Deref, DerefMut};
+use std::path::{Component, Path};
+use std::path::MAIN_SEPARATOR_STR;
 use std::path::PathBuf;
+use std::process;
+use std::slice;
 use std::str;
+use std::string::FromUtf8Error;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
-use frontmatter;
-use hoedown::{Markdown, Render};
-use hoedown::renderer::html::{self, Html};
+use chrono::NaiveDate;
+use hoedown::{Extension, FOOTNOTES, Markdown};
+use hoedown::renderer::html;
+use sha2::{Digest, Sha256};
 use walkdir::WalkDir;
-use yaml_rust::YamlEmitter;
-use yaml_rust::yaml::Yaml;
+use yaml_rust::yaml::{self, Yaml};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "tokio")]
+mod async_load;
+mod builder;
+mod emoji;
+mod error;
+mod feed;
+mod markdown;
+mod meta;
+mod render;
+mod template;
+mod tree;
+mod watch;
+
+#[cfg(feature = "tokio")]
+pub use self::async_load::LoadWiki;
+pub use self::builder::WikiBuilder;
+pub use self::error::WikiError;
+pub use self::meta::FrontmatterFormat;
+pub use self::render::MarkdownOptions;
+pub use self::template::Template;
+pub use self::tree::TreeNode;
+pub use self::watch::WikiEvent;
+
+/// The file extension of pages if no other extensions are configured
+const DEFAULT_EXTENSION: &str = "md";
+
+/// The name of the page representing a directory
+const DEFAULT_INDEX_NAME: &str = "index";
+
+/// The reading speed used by `Page::reading_time`
+const DEFAULT_WORDS_PER_MINUTE: usize = 200;
+
+/// Returns the extension of the given path if it is one of the given page
+/// extensions
+fn page_extension<'a, S: AsRef<str>>(path: &str, extensions: &'a [S]) -> Option<&'a str> {
+    extensions.iter()
+        .map(|extension| extension.as_ref())
+        .find(|extension| {
+            path.len() > extension.len()
+                && path.ends_with(extension)
+                && path[..path.len() - extension.len()].ends_with('.')
+        })
+}
+
+/// Normalizes the URL of a page to start with a single slash, repeated
+/// slashes are collapsed and a trailing slash is removed, i.e. `docs//page/`
+/// becomes `/docs/page`
+fn normalize_url(url: &str) -> String {
+    let segments: Vec<&str> = url.split('/').filter(|s| !s.is_empty()).collect();
+    format!("/{}", segments.join("/"))
+}
 
 /// Will strip the path to a file from the base path and the file extension
-fn convert_path_to_url(base_path: &str, path: &str) -> String {
-    let url = String::from(path);
-    let url = url.trim_left_matches(base_path);
-    let url = url.trim_right_matches(".md");
+///
+/// Only an exact leading occurrence of `base_path` and a single trailing
+/// extension out of `extensions` are removed, everything else is left
+/// untouched. Path separators are normalized to forward slashes, so the
+/// resulting URL is identical regardless of the platform, and the URL is
+/// normalized using `normalize_url`.
+fn convert_path_to_url<S: AsRef<str>>(base_path: &str, path: &str, extensions: &[S]) -> String {
+    let base_path = base_path.replace('\\', "/");
+    let path = path.replace('\\', "/");
+    let url = match path.strip_prefix(base_path.as_str()) {
+        Some(relative) => relative,
+        None => path.as_str()
+    };
+    let url = match page_extension(url, extensions) {
+        Some(extension) => &url[..url.len() - extension.len() - 1],
+        None => url
+    };
+
+    normalize_url(url)
+}
 
-    String::from(url)
+/// Same as `convert_path_to_url` but operates on paths, which do not have
+/// to be valid utf-8
+/// # Errors
+/// This will return `WikiError::InvalidUrl` if either of the paths can not
+/// be represented as utf-8
+fn try_convert_path_to_url<S: AsRef<str>>(base_path: &Path, path: &Path, extensions: &[S])
+                                          -> Result<String, WikiError> {
+    match (base_path.to_str(), path.to_str()) {
+        (Some(base_path), Some(path)) => Ok(convert_path_to_url(base_path, path, extensions)),
+        _ => Err(WikiError::InvalidUrl(path.to_string_lossy().into_owned()))
+    }
+}
+
+/// Returns true if every segment of the normalized URL is a plain file or
+/// directory name, which can not point outside of the directory it is
+/// joined onto
+fn is_plain_url(url: &str) -> bool {
+    url.split('/').skip(1).all(|segment| {
+        !segment.contains(['\\', '\0'])
+            && Path::new(segment).components().all(|c| matches!(c, Component::Normal(_)))
+    })
 }
 
-/// Will prefix the given URL with the base path and a file extension
-fn convert_url_to_path(base_path: &str, url: &str) -> String {
+/// Will prefix the given URL with the base path and suffix it with the
+/// given file extension
+///
+/// Forward slashes in the URL are translated to the separator of the
+/// platform.
+/// # Errors
+/// This will return `WikiError::InvalidUrl` if the URL would point outside
+/// of the base path, i.e. because it contains `..` segments, backslashes or
+/// a drive prefix, or if it is empty or only `/`, which has no file name
+fn convert_url_to_path(base_path: &str, url: &str, extension: &str)
+                       -> Result<String, WikiError> {
+    let relative = normalize_url(url);
+    if relative == "/" || !is_plain_url(relative.as_str()) {
+        return Err(WikiError::InvalidUrl(String::from(url)));
+    }
+
     let mut path = String::new();
     path.push_str(base_path);
-    path.push_str(url);
-    path.push_str(".md");
+    path.push_str(relative.replace('/', MAIN_SEPARATOR_STR).as_str());
+    path.push('.');
+    path.push_str(extension);
+
+    if !Path::new(path.as_str()).starts_with(base_path) {
+        return Err(WikiError::InvalidUrl(String::from(url)));
+    }
+    Ok(path)
+}
+
+/// Writes the contents to a temporary file next to the given path and
+/// renames it over the path once it has been synced, so readers either see
+/// the old or the new contents but never a partially written file
+/// # Errors
+/// This will return an error if writing or renaming the temporary file
+/// fails, the temporary file is removed in that case
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), io::Error> {
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name.to_string_lossy(),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))
+    };
+    let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, process::id()));
+    let result = File::create(tmp_path.as_path())
+        .and_then(|mut f| f.write_all(contents).and_then(|_| f.sync_all()))
+        .and_then(|_| match fs::metadata(path) {
+            // keep the permissions of the file that is replaced
+            Ok(metadata) => fs::set_permissions(tmp_path.as_path(), metadata.permissions()),
+            Err(_) => Ok(())
+        })
+        .and_then(|_| fs::rename(tmp_path.as_path(), path));
+    if result.is_err() {
+        let _ = fs::remove_file(tmp_path.as_path());
+    }
+    result
+}
+
+/// Returns whether the link target points outside of the wiki, i.e. has a
+/// scheme like `http:` or `mailto:` or is protocol relative
+fn is_external_link(target: &str) -> bool {
+    if target.starts_with("//") {
+        return true;
+    }
+    match target.find(':') {
+        Some(i) => !target[..i].contains('/'),
+        None => false
+    }
+}
+
+/// Resolves the target of a link on the page with the given URL to the URL
+/// of a page within the wiki, relative targets are resolved against the
+/// directory of the page. Returns `None` for external links and links to
+/// anchors on the same page
+fn resolve_link(page_url: &str, target: &str) -> Option<String> {
+    let target = target.split(['#', '?']).next().unwrap_or("");
+    if target.is_empty() || is_external_link(target) {
+        return None;
+    }
+    let target = target.replace("%20", " ");
+    let target = match target.strip_suffix(".md") {
+        Some(stripped) => stripped,
+        None => target.as_str()
+    };
+
+    let mut segments: Vec<&str> = Vec::new();
+    if !target.starts_with('/') {
+        segments.extend(page_url.split('/').filter(|s| !s.is_empty()));
+        segments.pop();
+    }
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => { segments.pop(); }
+            segment => segments.push(segment)
+        }
+    }
+    Some(format!("/{}", segments.join("/")))
+}
+
+/// Pairs the headings with unique anchor ids to the entries of a table of
+/// contents, see `Page::toc`
+fn toc_entries(headings: Vec<markdown::Heading>) -> Vec<(u8, String, String)> {
+    let slugs = markdown::unique_slugs(&headings);
+    headings.into_iter()
+        .zip(slugs)
+        .map(|(heading, slug)| (heading.level, heading.text, slug))
+        .collect()
+}
+
+/// Turns a relative link target on the page with the given URL into an
+/// absolute URL within the wiki, keeping any query or anchor. Returns `None`
+/// for targets that are absolute or external already
+fn absolute_href(page_url: &str, href: &str) -> Option<String> {
+    let split = href.find(['#', '?']).unwrap_or(href.len());
+    let (target, suffix) = href.split_at(split);
+    if target.is_empty() || target.starts_with('/') || is_external_link(target) {
+        return None;
+    }
+    resolve_link(page_url, target).map(|url| format!("{}{}", url.replace(' ', "%20"), suffix))
+}
+
+/// Matches the text against a glob pattern, `*` and `?` match any number
+/// of characters or a single character within a segment of the path and
+/// `**` matches across segments, i.e. `docs/**/*.md`
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) if rest.first() == Some(&b'*') => {
+            let rest = &rest[1..];
+            // `a/**/b` also matches `a/b`
+            if let Some(after) = rest.strip_prefix(b"/") {
+                if glob_match(after, text) {
+                    return true;
+                }
+            }
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some((b'*', rest)) => {
+            let segment = text.iter().position(|&c| c == b'/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some((b'?', rest)) => {
+            !text.is_empty() && text[0] != b'/' && glob_match(rest, &text[1..])
+        }
+        Some((&c, rest)) => text.first() == Some(&c) && glob_match(rest, &text[1..])
+    }
+}
+
+/// Returns whether the path relative to the root of the wiki, separated by
+/// slashes, matches one of the exclude patterns. Like in a `.gitignore`,
+/// patterns containing a slash are matched against the whole path while
+/// other patterns are matched against every file and directory name
+fn is_excluded<S: AsRef<str>>(relative: &str, patterns: &[S]) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.as_ref();
+        let anchored = pattern.trim_start_matches('/');
+        if pattern.contains('/') {
+            glob_match(anchored.trim_end_matches('/').as_bytes(), relative.as_bytes())
+        } else {
+            relative.split('/').any(|name| glob_match(pattern.as_bytes(), name.as_bytes()))
+        }
+    })
+}
+
+/// Makes the text end with exactly one line ending unless it is empty,
+/// optionally converting `\r\n` line endings to `\n` first
+fn normalize_newlines(text: &str, crlf_to_lf: bool) -> String {
+    let text = if crlf_to_lf {
+        text.replace("\r\n", "\n")
+    } else {
+        String::from(text)
+    };
+    let ending = if text.ends_with("\r\n") { "\r\n" } else { "\n" };
+    let trimmed = text.trim_end_matches(['\r', '\n']);
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("{}{}", trimmed, ending)
+    }
+}
+
+/// Returns the number of single character insertions, deletions and
+/// substitutions needed to turn one string into the other
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current[j + 1] = cmp::min(substitution, cmp::min(previous[j + 1], current[j]) + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Converts scalar YAML values to a string, returns `None` for any other
+/// kind of value
+fn yaml_to_string(yaml: &Yaml) -> Option<String> {
+    match *yaml {
+        Yaml::String(ref s) | Yaml::Real(ref s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        _ => None
+    }
+}
+
+/// Shortens the text to at most `max_chars` characters including a
+/// trailing ellipsis, cutting at a word boundary if possible
+fn truncate_words(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return String::from(text);
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+    let cut = text.char_indices().nth(max_chars - 1).map_or(text.len(), |(i, _)| i);
+    let at_boundary = text[cut..].starts_with(char::is_whitespace);
+    let truncated = match text[..cut].rfind(char::is_whitespace) {
+        Some(i) if !at_boundary => &text[..i],
+        _ => &text[..cut]
+    };
+    format!("{}\u{2026}", truncated.trim_end())
+}
 
-    path
+/// Decodes the contents of a file that is not valid utf-8 as windows-1252,
+/// which is a superset of latin-1 and was the default of many editors. The
+/// page is written back as utf-8 once it is saved. This never fails
+#[cfg(feature = "encoding_rs")]
+fn decode_fallback(path: &Path, e: FromUtf8Error) -> Result<String, io::Error> {
+    let (decoded, _, _) = encoding_rs::WINDOWS_1252.decode(e.as_bytes());
+    println!("Decoded {} as windows-1252, it is not valid utf-8", path.display());
+    Ok(decoded.into_owned())
+}
+
+/// Rejects the contents of a file that is not valid utf-8, enable the
+/// `encoding_rs` feature to decode those as windows-1252 instead
+/// # Errors
+/// This always returns an error of kind `InvalidData`
+#[cfg(not(feature = "encoding_rs"))]
+fn decode_fallback(_path: &Path, e: FromUtf8Error) -> Result<String, io::Error> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The contents of a page file, split into the frontmatter and the markdown
+struct PageSource {
+    /// the raw body of the file
+    raw: String,
+    /// the frontmatter, might be empty
+    frontmatter: Option<meta::Frontmatter>,
+    /// the markdown following the frontmatter
+    markdown: String,
+    /// the time the file has been modified, if known
+    modified: Option<SystemTime>,
+}
+
+impl PageSource {
+    /// Reads the contents of the file at the given path and interprets them
+    /// # Errors
+    /// This will return an error if i.e. the reading of the file fails
+    /// because of lacking permissions or non utf-8 content or
+    /// `WikiError::Frontmatter` if the frontmatter is not valid YAML or TOML
+    fn read(path: &Path) -> Result<PageSource, WikiError> {
+        let (raw, modified) = PageSource::read_raw(path)?;
+        let mut source = PageSource::parse(raw)?;
+        source.modified = modified;
+        Ok(source)
+    }
+
+    /// Same as `read`, but only interprets the contents if their hash
+    /// differs from the given one of the contents that are already loaded
+    /// # Errors
+    /// See `read`
+    fn reread(path: &Path, content_hash: Option<u64>) -> Result<Reread, WikiError> {
+        let (raw, modified) = PageSource::read_raw(path)?;
+        if content_hash == Some(hash_content(raw.as_str())) {
+            return Ok(Reread::Unchanged(modified));
+        }
+        let mut source = PageSource::parse(raw)?;
+        source.modified = modified;
+        Ok(Reread::Changed(source))
+    }
+
+    /// Reads the contents of the file at the given path along with the time
+    /// it has been modified, without interpreting them
+    /// # Errors
+    /// See `read`
+    fn read_raw(path: &Path) -> Result<(String, Option<SystemTime>), WikiError> {
+        let mut f = File::open(path)?;
+        let mut bytes = Vec::new();
+        f.read_to_end(&mut bytes)?;
+        let mut buffer = match String::from_utf8(bytes) {
+            Ok(buffer) => buffer,
+            Err(e) => decode_fallback(path, e)?
+        };
+        // some editors on windows start files with a byte order mark, which
+        // would hide the frontmatter delimiter
+        if buffer.starts_with('\u{feff}') {
+            buffer.drain(..'\u{feff}'.len_utf8());
+        }
+        Ok((buffer, f.metadata().and_then(|m| m.modified()).ok()))
+    }
+
+    /// Splits the raw contents of a file into the frontmatter and markdown
+    /// # Errors
+    /// This will return `WikiError::Frontmatter` if the frontmatter is not
+    /// valid YAML or TOML
+    fn parse(raw: String) -> Result<PageSource, WikiError> {
+        let (frontmatter, markdown) = meta::parse(raw.as_str())?;
+        let markdown = String::from(markdown);
+        Ok(PageSource { raw, frontmatter, markdown, modified: None })
+    }
+
+    /// Reads the files at the given paths using `read` spread across several
+    /// threads, the results are in the same order as the paths
+    fn read_all<T, F>(paths: &[PathBuf], read: F) -> Vec<Result<T, WikiError>>
+        where T: Send, F: Fn(&Path) -> Result<T, WikiError> + Sync {
+        let read = &read;
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = cmp::max(1, paths.len().div_ceil(threads));
+        thread::scope(|scope| {
+            let workers: Vec<_> = paths.chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || {
+                    chunk.iter()
+                        .map(|path| read(path.as_path()))
+                        .collect::<Vec<_>>()
+                }))
+                .collect();
+            workers.into_iter()
+                .flat_map(|worker| worker.join().expect("page loading thread panicked"))
+                .collect()
+        })
+    }
+}
+
+/// The contents of a page file that is read again
+enum Reread {
+    /// the contents are the same as when the page was loaded, only the time
+    /// the file has been modified is refreshed
+    Unchanged(Option<SystemTime>),
+    /// the contents have changed and need to be loaded
+    Changed(PageSource),
+}
+
+/// Hashes the raw contents of a page file to tell whether it has changed
+fn hash_content(raw: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    raw.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A link found on a page
+#[derive(Clone, Debug, PartialEq)]
+pub enum Link {
+    /// a link to a page within the wiki, the URL is resolved against the
+    /// URL of the page the link is on
+    Internal { url: String, text: String },
+    /// a link leaving the wiki, i.e. `https://example.com` or `mailto:`
+    External { url: String, text: String },
+    /// a link to an anchor on the same page, without the leading `#`
+    Anchor { anchor: String, text: String },
+    /// an image with its source as it is written in the markdown
+    Image { src: String, alt: String },
+}
+
+/// A heading of a page along with the headings nested below it, see
+/// `Page::headings_outline`
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineNode {
+    /// the level of the heading, 1 to 6
+    pub level: u8,
+    /// the text of the heading as written in the markdown
+    pub text: String,
+    /// the anchor id of the heading
+    pub anchor: String,
+    /// the headings following this one with a deeper level, up to the next
+    /// heading of the same or a higher level
+    pub children: Vec<OutlineNode>,
+}
+
+impl OutlineNode {
+    /// Builds the nodes of the headings starting at `next` that are nested
+    /// deeper than `parent_level`, advancing `next` past them
+    fn build(toc: &[(u8, String, String)], next: &mut usize, parent_level: u8)
+             -> Vec<OutlineNode> {
+        let mut nodes = Vec::new();
+        while let Some(&(level, ref text, ref anchor)) = toc.get(*next) {
+            if level <= parent_level {
+                break;
+            }
+            *next += 1;
+            let children = OutlineNode::build(toc, next, level);
+            nodes.push(OutlineNode {
+                level,
+                text: text.clone(),
+                anchor: anchor.clone(),
+                children,
+            });
+        }
+        nodes
+    }
+}
+
+/// Where `Page::insert_section` puts the content within the section
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Position {
+    /// after the existing content of the section
+    Append,
+    /// right below the heading, before the existing content
+    Prepend,
+}
+
+/// The length of the summary included in `PageInfo`
+const INFO_SUMMARY_LENGTH: usize = 200;
+
+/// A summary of the metadata of a page, i.e. to list the pages of a wiki in
+/// an API. With the `serde` feature enabled this can be serialized
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PageInfo {
+    /// the url of the page
+    pub url: String,
+    /// the title of the page, see `Page::title`
+    pub title: String,
+    /// the tags of the page, see `Page::tags`
+    pub tags: Vec<String>,
+    /// the time the backing file has been modified, if it exists
+    pub last_modified: Option<SystemTime>,
+    /// a plain text excerpt of the page, see `Page::summary`
+    pub summary: String,
 }
 
 /// A single page within the wiki, which is backed by a markdown file
 /// on disk
+#[derive(Clone)]
 pub struct Page {
     /// the root path of the wiki this page is a part of
     pub base_path: PathBuf,
@@ -45,14 +569,35 @@ pub struct Page {
     /// the raw body of the file, may be empty if the page has not been
     /// written to disk yet
     raw: String,
-    /// the YAML frontmatter, might be empty
+    /// the frontmatter, might be empty. TOML frontmatter is converted to
+    /// YAML when the page is loaded
     pub meta: Option<Yaml>,
+    /// the format the frontmatter is written in when the page is saved,
+    /// pages that are read from disk keep the format of their file
+    pub frontmatter_format: FrontmatterFormat,
+    /// the frontmatter as it was last read or written, it is written back
+    /// verbatim as long as neither `meta` nor the format change
+    frontmatter: Option<meta::Frontmatter>,
+    /// whether `\r\n` line endings are converted to `\n` when the page is
+    /// saved, defaults to `true`
+    pub normalize_line_endings: bool,
     /// the raw markdown body of the page, might be an empty string
     pub markdown_raw: String,
-    /// the markdown body of the page
-    markdown: Markdown,
-    /// the compiled HTML of the page
-    pub html: String
+    /// the options used to render the markdown to html
+    pub markdown_options: MarkdownOptions,
+    /// the targets of wikilinks on this page that do not exist within the
+    /// wiki, only tracked if enabled in the markdown options
+    missing_links: Vec<String>,
+    /// the compiled HTML of the page, rendered on first access
+    html: OnceLock<String>,
+    /// whether the page has been modified since it was last saved
+    dirty: bool,
+    /// the time the backing file has been modified when it was last read
+    /// or written
+    modified: Option<SystemTime>,
+    /// the hash of `raw` when it was last read or written, as long as it
+    /// matches the file the page is not loaded and rendered again
+    content_hash: Option<u64>,
 }
 
 impl Page {
@@ -61,227 +606,4454 @@ impl Page {
     /// frontmatter and HTML
     /// # Errors
     /// This will return an error if i.e. the reading of the file fails
-    /// because of lacking permissions or non utf-8 content
-    pub fn from_file(base_path: PathBuf, path: PathBuf) -> Result<Page, io::Error> {
+    /// because of lacking permissions or non utf-8 content, if the path is
+    /// not valid utf-8 or if the frontmatter can not be parsed
+    pub fn from_file(base_path: PathBuf, path: PathBuf) -> Result<Page, WikiError> {
+        Page::from_file_with_options(base_path, path, MarkdownOptions::default())
+    }
 
-        let url = convert_path_to_url(
-            base_path.to_str().unwrap(),
-            path.to_str().unwrap()
-        );
+    /// Same as `from_file`, but renders the markdown using the given options
+    /// # Errors
+    /// See `from_file`
+    pub fn from_file_with_options(base_path: PathBuf, path: PathBuf,
+                                  markdown_options: MarkdownOptions)
+                                  -> Result<Page, WikiError> {
+        let url = try_convert_path_to_url(base_path.as_path(), path.as_path(),
+                                          &[DEFAULT_EXTENSION])?;
+        let source = PageSource::read(path.as_path())?;
+        Ok(Page::from_source(base_path, path, url, source, markdown_options,
+                             FrontmatterFormat::default()))
+    }
 
-        let mut page = Page{
-            base_path: base_path,
-            path: path,
-            url: String::from(url),
-            raw: String::from(""),
-            meta: None,
-            markdown_raw: String::from(""),
-            markdown: Markdown::new(""),
-            html: String::from(""),
+    /// If you want to just create a new page object without reading
+    /// it from disk (i.e. if you want to create a new page) use this. The
+    /// backing file is derived from the URL and only written by
+    /// `save_to_file`, until then `raw` stays empty
+    /// # Errors
+    /// This will return `WikiError::InvalidUrl` if the given base path is
+    /// not valid utf-8, the URL points outside of it, i.e. `/../secret`, or
+    /// the URL is empty
+    pub fn new(base_path: PathBuf, url: &str, markdown: &str, meta: Option<Yaml>)
+               -> Result<Page, WikiError> {
+        let path = match base_path.to_str() {
+            Some(base) => convert_url_to_path(base, url, DEFAULT_EXTENSION)?,
+            None => return Err(WikiError::InvalidUrl(String::from(url)))
         };
-        try!(page.read_from_file());
-        page.load();
+        let url = normalize_url(url);
+        let mut page = Page::empty(base_path, PathBuf::from(path), url,
+                                   MarkdownOptions::default(), FrontmatterFormat::default());
+        page.meta = meta;
+        page.update_markdown(markdown);
         Ok(page)
     }
 
-    /// If you want to just create a new page object without reading
-    /// it from disk (i.e. if you want to create a new page) use this
-    pub fn new(base_path: PathBuf, path: PathBuf) -> Page {
-        let url = convert_path_to_url(
-            base_path.to_str().unwrap(),
-            path.to_str().unwrap()
-        );
+    /// Creates an empty `Page` object with the given URL
+    fn empty(base_path: PathBuf, path: PathBuf, url: String,
+             markdown_options: MarkdownOptions, frontmatter_format: FrontmatterFormat) -> Page {
         Page{
-            base_path: base_path,
-            path: path,
-            url: String::from(url),
+            base_path,
+            path,
+            url,
             raw: String::from(""),
             meta: None,
+            frontmatter_format,
+            frontmatter: None,
+            normalize_line_endings: true,
             markdown_raw: String::from(""),
-            markdown: Markdown::new(""),
-            html: String::from(""),
+            markdown_options,
+            missing_links: Vec::new(),
+            html: OnceLock::new(),
+            dirty: true,
+            modified: None,
+            content_hash: None,
         }
     }
 
-    /// Reads the contents of the underlying files from the disk
+    /// Re-reads the page from the disk, refreshing the frontmatter, the
+    /// markdown and the HTML. If the file has not changed since it was
+    /// loaded and the page has no unsaved modifications, the page is kept
+    /// as it is including its rendered HTML
     /// # Errors
-    /// This will return an error if i.e. the reading of the file fails
-    /// because of lacking permissions or non utf-8 content
-    fn read_from_file(&mut self) -> Result<(), io::Error> {
-        let mut f = try!(File::open(self.path.as_path()));
-        let mut buffer = String::new();
-        try!(f.read_to_string(&mut buffer));
-        self.raw = buffer;
+    /// See `from_file`
+    pub fn reload(&mut self) -> Result<(), WikiError> {
+        match PageSource::reread(self.path.as_path(), self.loaded_hash())? {
+            Reread::Unchanged(modified) => self.modified = modified,
+            Reread::Changed(source) => self.load(source)
+        }
         Ok(())
     }
 
-    /// Interprets the raw data, among other things loading the frontmatter
-    /// and converting markdown to html.
-    fn load(&mut self) {
-        let raw = self.raw.clone();
-        match frontmatter::parse_and_find_content(raw.as_str()) {
-            Ok((meta, markdown)) => {
-                self.meta = meta;
-                self.update_markdown(markdown);
-            }
-            Err(_) => ()
+    /// Returns the hash of the contents of the backing file if the page
+    /// still reflects them, i.e. has no unsaved modifications
+    fn loaded_hash(&self) -> Option<u64> {
+        if self.dirty {
+            None
+        } else {
+            self.content_hash
+        }
+    }
+
+    /// Creates a new `Page` object from contents that have already been
+    /// read from the backing file
+    fn from_source(base_path: PathBuf, path: PathBuf, url: String, source: PageSource,
+                   markdown_options: MarkdownOptions, frontmatter_format: FrontmatterFormat)
+                   -> Page {
+        let mut page = Page::empty(base_path, path, url, markdown_options, frontmatter_format);
+        page.load(source);
+        page
+    }
+
+    /// Takes over the raw data, the frontmatter and the markdown read from
+    /// the backing file
+    fn load(&mut self, source: PageSource) {
+        self.raw = source.raw;
+        self.meta = source.frontmatter.as_ref().map(|frontmatter| frontmatter.meta.clone());
+        if let Some(ref frontmatter) = source.frontmatter {
+            self.frontmatter_format = frontmatter.format;
         }
+        self.frontmatter = source.frontmatter;
+        self.modified = source.modified;
+        self.content_hash = Some(hash_content(self.raw.as_str()));
+        self.update_markdown(source.markdown.as_str());
+        self.dirty = false;
+    }
+
+    /// Renders the markdown of the page using the given renderer flags and
+    /// extensions instead of the markdown options, i.e. for a one-off
+    /// export. Unlike `html` the result is not cached and no heading ids or
+    /// other post processing are added
+    pub fn render_with(&self, flags: html::Flags, extensions: Extension) -> String {
+        let markdown = markdown::expand_wikilinks(self.markdown_raw.as_str());
+        let document = Markdown::new(markdown.as_str()).extensions(extensions);
+        render::render_with_flags(&document, flags)
     }
 
-    /// Updates the markdown contents of the file and automatically
-    /// re-renders the html accordingly.
+    /// Updates the markdown contents of the file, the html will be
+    /// re-rendered accordingly the next time it is accessed. Nothing is
+    /// rendered here, so many edits in a row only cost a single render.
+    /// Missing links that are no longer on the page are forgotten
     pub fn update_markdown(&mut self, markdown: &str) {
-        let mut html = Html::new(html::Flags::empty(), 0);
+        let links = markdown::wikilinks(markdown);
+        self.missing_links.retain(|url| links.iter().any(|link| &link.target == url));
         self.markdown_raw = String::from(markdown);
-        self.markdown = Markdown::new(markdown);
-        self.html = String::from(
-            html.render(&self.markdown).to_str().unwrap()
-        );
+        self.html = OnceLock::new();
+        self.dirty = true;
+    }
+
+    /// Sets the markdown of the page and marks it as dirty without rendering
+    /// it or looking at its links, the missing links are kept as they are
+    /// until the wiki checks them again. Use this when making several edits
+    /// before a single final render
+    pub fn set_markdown_raw(&mut self, md: &str) {
+        self.markdown_raw = String::from(md);
+        self.html = OnceLock::new();
+        self.dirty = true;
+    }
+
+    /// Inserts the content into the section below the first heading with
+    /// the given text, the section ends at the next heading of the same or
+    /// a higher level. If there is no such heading, a level 2 heading with
+    /// the content is added to the end of the page
+    pub fn insert_section(&mut self, heading: &str, content: &str, position: Position) {
+        let markdown = markdown::insert_section(self.markdown_raw.as_str(), heading, content,
+                                                position == Position::Append);
+        self.update_markdown(markdown.as_str());
+    }
+
+    /// Renders the markdown to HTML without any of the post processing. The
+    /// headings and their anchors are taken from this, so they always match
+    /// the headings hoedown renders
+    fn render_base(&self) -> String {
+        let markdown = markdown::expand_wikilinks(self.markdown_raw.as_str());
+        let markdown = if self.markdown_options.emoji {
+            markdown::expand_emoji(markdown.as_str())
+        } else {
+            markdown
+        };
+        let document = render::prepare(markdown.as_str(), &self.markdown_options);
+        render::render_html(&document, &self.markdown_options)
+    }
+
+    /// Returns the compiled HTML of the page. The markdown is only rendered
+    /// the first time this is called, afterwards the cached result is used
+    pub fn html(&self) -> &str {
+        self.html.get_or_init(|| {
+            let html = self.render_base();
+            let toc = toc_entries(render::headings(html.as_str()));
+            let slugs: Vec<String> = toc.iter().map(|(_, _, slug)| slug.clone()).collect();
+            let html = render::add_heading_ids(html.as_str(), &slugs);
+            let html = if markdown::has_toc_marker(self.markdown_raw.as_str()) {
+                render::insert_toc(html.as_str(), render::toc_html(&toc).as_str())
+            } else {
+                html
+            };
+            let html = if self.markdown_options.heading_offset > 0 {
+                render::shift_headings(html.as_str(), self.markdown_options.heading_offset)
+            } else {
+                html
+            };
+            let html = if self.missing_links.is_empty() {
+                html
+            } else {
+                let links = markdown::wikilinks(self.markdown_raw.as_str());
+                render::mark_missing_links(
+                    html.as_str(),
+                    &links,
+                    |url| !self.missing_links.iter().any(|missing| missing == url)
+                )
+            };
+            let html = if self.markdown_options.all_extensions().contains(FOOTNOTES) {
+                render::fix_footnote_links(html.as_str())
+            } else {
+                html
+            };
+            let html = if self.markdown_options.definition_lists {
+                render::definition_lists(html.as_str())
+            } else {
+                html
+            };
+            let html = if self.markdown_options.code_classes {
+                render::add_code_classes(html.as_str())
+            } else {
+                html
+            };
+            let html = if self.markdown_options.resolve_relative_links {
+                render::rewrite_links(html.as_str(), |href| absolute_href(self.url.as_str(), href))
+            } else {
+                html
+            };
+            if self.markdown_options.resolve_relative_images {
+                render::rewrite_images(html.as_str(), |src| absolute_href(self.url.as_str(), src))
+            } else {
+                html
+            }
+        })
+    }
+
+    /// Sets the wikilink targets of this page that do not exist, discarding
+    /// the html if they changed
+    fn set_missing_links(&mut self, missing_links: Vec<String>) {
+        if self.missing_links != missing_links {
+            self.missing_links = missing_links;
+            self.html = OnceLock::new();
+        }
     }
 
     /// Use this method after having modified the page to update the internal
-    /// raw representation.
+    /// raw representation. The frontmatter is left out entirely if there
+    /// is none and only re-serialized if it has been changed, so the key
+    /// order and comments of the file are kept. The markdown is written
+    /// ending with exactly one newline unless it is empty
     fn update_raw(&mut self) {
+        if let Some(meta) = self.meta.as_ref() {
+            if !self.frontmatter_unchanged() {
+                self.frontmatter = Some(meta::Frontmatter {
+                    meta: meta.clone(),
+                    format: self.frontmatter_format,
+                    text: meta::emit(meta, self.frontmatter_format),
+                });
+            }
+        }
+        self.raw = self.serialize();
+    }
+
+    /// Returns whether the frontmatter is the one last read or written, in
+    /// which case its text can be written back verbatim
+    fn frontmatter_unchanged(&self) -> bool {
+        self.frontmatter.as_ref().is_some_and(|frontmatter| {
+            self.meta.as_ref() == Some(&frontmatter.meta)
+                && frontmatter.format == self.frontmatter_format
+        })
+    }
+
+    /// Returns the raw contents the backing file has once the page is saved
+    fn serialize(&self) -> String {
         let mut buffer = String::new();
 
-        buffer.push_str("---\n");
-        match self.meta.as_ref() {
-            Some(yaml) => {
-                let mut meta_str = String::new();
-                {
-                    let mut emitter = YamlEmitter::new(&mut meta_str);
-                    emitter.dump(&yaml).unwrap();
-                }
-                buffer.push_str(meta_str.as_str());
+        if let Some(meta) = self.meta.as_ref() {
+            let text = if self.frontmatter_unchanged() {
+                self.frontmatter.as_ref().unwrap().text.clone()
+            } else {
+                meta::emit(meta, self.frontmatter_format)
+            };
+            if self.normalize_line_endings {
+                buffer.push_str(text.replace("\r\n", "\n").as_str());
+            } else {
+                buffer.push_str(text.as_str());
             }
-            None => ()
         }
-        buffer.push_str("---\n");
 
-        buffer.push_str(self.markdown_raw.as_str());
+        buffer.push_str(
+            normalize_newlines(self.markdown_raw.as_str(), self.normalize_line_endings).as_str()
+        );
+        buffer
+    }
 
-        self.raw = buffer;
+    /// Returns the hex encoded SHA-256 of the raw contents of the page, which
+    /// are the contents of the file as they have been loaded or last saved.
+    /// Unsaved changes are not included and for utf-8 files without a byte
+    /// order mark this is the same as the checksum of the file on disk
+    pub fn checksum(&self) -> String {
+        let digest = Sha256::digest(self.raw.as_bytes());
+        digest.iter().map(|byte| format!("{:02x}", byte)).collect()
     }
 
-    /// Will write the current raw data to the underlying file system
+    /// Will write the current raw data to the underlying file system. Pages
+    /// that have not been modified since they were loaded are not written.
+    /// The file is replaced atomically, so it is never left half written
     /// # Errors
     /// Might fail due to io related errors, i.e. permissions or disk space
-    pub fn save_to_file(&mut self) -> Result<(), io::Error> {
+    pub fn save_to_file(&mut self) -> Result<(), WikiError> {
+        if !self.dirty {
+            return Ok(());
+        }
         self.update_raw();
-        let mut f = try!(File::create(self.path.as_path()));
-        try!(f.write_all(self.raw.as_bytes()));
-        try!(f.sync_all());
+        write_atomically(self.path.as_path(), self.raw.as_bytes())?;
+        self.dirty = false;
+        self.content_hash = Some(hash_content(self.raw.as_str()));
+        self.modified = fs::metadata(self.path.as_path()).and_then(|m| m.modified()).ok();
         Ok(())
     }
 
-}
+    /// Returns the time the backing file has been modified. The time is
+    /// remembered when the file is read or written, so changes made by
+    /// other programs are only picked up by `reload`
+    /// # Errors
+    /// This will return an error if the file does not exist or its
+    /// metadata can not be read
+    pub fn last_modified(&self) -> Result<SystemTime, io::Error> {
+        match self.modified {
+            Some(modified) => Ok(modified),
+            None => fs::metadata(self.path.as_path()).and_then(|m| m.modified())
+        }
+    }
 
-/// A wiki object
-pub struct Wiki {
-    /// the root path of the wiki
-    pub path: PathBuf,
-    /// the pages that are contained in this wiki
-    pub pages: Vec<Page>,
-}
+    /// Returns whether the page has been modified since it was last saved
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
 
-impl Wiki {
-    /// Creates a new `Wiki` object. Will automatically load all pages
-    /// that are contained
-    pub fn new(pathname: &str) -> Wiki {
-        let mut wiki = Wiki {
-            path: Path::new(pathname).to_path_buf(),
-            pages: Vec::new(),
-        };
-        wiki.load_pages();
-        wiki
+    /// Returns the frontmatter serialized as JSON, i.e. for scripts in the
+    /// browser. An empty object is returned if there is no frontmatter
+    pub fn frontmatter_as_json(&self) -> String {
+        match self.meta.as_ref() {
+            Some(meta) => meta::to_json(meta),
+            None => String::from("{}")
+        }
     }
 
-    /// Load all the pages in the wiki
-    fn load_pages(&mut self) {
-        // make sure we do not duplicate shit by clearing
-        // the vector first if necessary
-        if !self.pages.is_empty() {
-            self.pages.truncate(0);
+    /// Returns the value of the given key of the frontmatter, if the
+    /// frontmatter is a mapping and contains the key
+    pub fn get_meta(&self, key: &str) -> Option<&Yaml> {
+        match self.meta {
+            Some(Yaml::Hash(ref hash)) => hash.get(&Yaml::String(String::from(key))),
+            _ => None
         }
+    }
 
-        for entry in WalkDir::new(&self.path) {
-            let entry = entry.unwrap();
-            let entry = entry.path();
-            let entry_path_str = entry.to_str().unwrap();
-            if entry.is_file() && entry_path_str.ends_with(".md") {
-                match Page::from_file(self.path.clone(),
-                                      entry.to_path_buf()) {
-                    Ok(page) => self.pages.push(page),
-                    Err(e) => println!(
-                        "Failed loading {}: {}",
-                        entry_path_str,
-                        e
-                    )
+    /// Returns the value at the given dotted path of the frontmatter, i.e.
+    /// `author.name` for the `name` key of the `author` mapping. Returns
+    /// `None` if any key along the path is missing or not a mapping
+    pub fn meta_path(&self, dotted_key: &str) -> Option<&Yaml> {
+        let mut value = self.meta.as_ref()?;
+        for key in dotted_key.split('.') {
+            value = match *value {
+                Yaml::Hash(ref hash) => hash.get(&Yaml::String(String::from(key)))?,
+                _ => return None
+            };
+        }
+        Some(value)
+    }
+
+    /// Checks that the frontmatter has a value for each of the given keys,
+    /// which may be dotted paths like for `meta_path`. Keys that are empty,
+    /// i.e. `author:` without a value, count as missing
+    /// # Errors
+    /// This will return the missing keys in the order they were given
+    pub fn validate_frontmatter(&self, required: &[&str]) -> Result<(), Vec<String>> {
+        let missing: Vec<String> = required.iter()
+            .filter(|key| match self.meta_path(key) {
+                None | Some(&Yaml::Null) => true,
+                Some(Yaml::String(value)) => value.trim().is_empty(),
+                Some(_) => false
+            })
+            .map(|key| String::from(*key))
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing)
+        }
+    }
+
+    /// Sets the given key of the frontmatter to the value, the frontmatter
+    /// is turned into a mapping first if it is not one already. The change
+    /// is written to disk by the next `save_to_file`, setting a key to the
+    /// value it already has does not modify the page
+    pub fn set_meta(&mut self, key: &str, value: Yaml) {
+        let key = Yaml::String(String::from(key));
+        match self.meta {
+            Some(Yaml::Hash(ref mut hash)) => {
+                if hash.get(&key) == Some(&value) {
+                    return;
                 }
+                hash.insert(key, value);
+            }
+            _ => {
+                let mut hash = yaml::Hash::new();
+                hash.insert(key, value);
+                self.meta = Some(Yaml::Hash(hash));
             }
         }
+        self.dirty = true;
     }
 
-    /// Will get an individual page object given a URL
-    pub fn get_page(&self, url: &str) -> Option<&Page> {
-        for page in self.pages.iter() {
-            if page.url == url {
-                return Some(page);
-            }
+    /// Returns a human readable title for the page. This is the `title` of
+    /// the frontmatter if there is one, otherwise the first level one
+    /// heading of the markdown and as a last resort the last segment of the
+    /// URL, i.e. `/docs/getting-started` becomes `Getting Started`
+    pub fn title(&self) -> String {
+        if let Some(title) = self.meta.as_ref().and_then(|meta| meta["title"].as_str()) {
+            return String::from(title);
+        }
+
+        let headings = markdown::headings(self.markdown_raw.as_str());
+        if let Some(heading) = headings.into_iter()
+            .find(|heading| heading.level == 1 && !heading.text.is_empty()) {
+            return heading.text;
         }
-        None
+
+        let segment = match self.url.split('/').rev().find(|s| !s.is_empty()) {
+            Some(segment) => segment,
+            None => return String::new()
+        };
+        let words: Vec<String> = segment
+            .split(['-', '_'])
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new()
+                }
+            })
+            .collect();
+        words.join(" ")
     }
 
-    /// Will create a new page within this wiki using the given URL
-    /// It will not be persisted to disk until you call `Page::save_to_file`
-    pub fn add_page(&self, url: &str) -> Page {
-        let path = convert_url_to_path(self.path.to_str().unwrap(), url);
-        Page::new(self.path.clone(), PathBuf::from(path))
+    /// Returns whether the page is a draft, which is the case if `draft` is
+    /// set to `true` in the frontmatter. Drafts are left out of the listings
+    /// of the wiki
+    pub fn is_draft(&self) -> bool {
+        match self.get_meta("draft") {
+            Some(&Yaml::Boolean(draft)) => draft,
+            _ => false
+        }
     }
 
-}
+    /// Returns the publishing date of the page as given by the `date` key of
+    /// the frontmatter in ISO 8601 format, i.e. `2016-05-01`. The time of a
+    /// full timestamp like `2016-05-01T12:00:00Z` is ignored
+    pub fn date(&self) -> Option<NaiveDate> {
+        let date = self.get_meta("date").and_then(yaml_to_string)?;
+        let date = date.trim().split(['T', ' ']).next()?;
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+    }
 
+    /// Returns the tags of the page as given by the `tags` key of the
+    /// frontmatter, which may either be a list or a single string
+    pub fn tags(&self) -> Vec<String> {
+        let tags = match self.meta.as_ref() {
+            Some(meta) => &meta["tags"],
+            None => return Vec::new()
+        };
+        match *tags {
+            Yaml::Array(ref tags) => tags.iter().filter_map(yaml_to_string).collect(),
+            _ => yaml_to_string(tags).into_iter().collect()
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    extern crate tempdir;
-    use self::tempdir::TempDir;
-    use super::Wiki;
+    /// Returns the alternative URLs of the page as given by the `aliases`
+    /// key of the frontmatter, i.e. the URLs the page had before it was
+    /// renamed. This may either be a list or a single string
+    pub fn aliases(&self) -> Vec<String> {
+        let aliases = match self.meta.as_ref() {
+            Some(meta) => &meta["aliases"],
+            None => return Vec::new()
+        };
+        let aliases: Vec<String> = match *aliases {
+            Yaml::Array(ref aliases) => aliases.iter().filter_map(yaml_to_string).collect(),
+            _ => yaml_to_string(aliases).into_iter().collect()
+        };
+        aliases.iter().map(|alias| normalize_url(alias)).collect()
+    }
 
-    fn make_tmp_wiki(dirname: &str) -> Wiki {
-        let dir = TempDir::new(dirname).expect("create temp dir");
-        let wiki = Wiki::new(dir.path().to_str().unwrap());
-        wiki
+    /// Returns all links and images on the page, first the inline links,
+    /// then the reference links and finally the wikilinks
+    pub fn links(&self) -> Vec<Link> {
+        let markdown = self.markdown_raw.as_str();
+        let mut links: Vec<Link> = markdown::inline_links(markdown).into_iter()
+            .chain(markdown::reference_links(markdown))
+            .filter_map(|link| {
+                if link.image {
+                    return Some(Link::Image { src: link.target, alt: link.text });
+                }
+                if let Some(anchor) = link.target.strip_prefix('#') {
+                    return Some(Link::Anchor { anchor: String::from(anchor), text: link.text });
+                }
+                if is_external_link(link.target.as_str()) {
+                    return Some(Link::External { url: link.target, text: link.text });
+                }
+                resolve_link(self.url.as_str(), link.target.as_str())
+                    .map(|url| Link::Internal { url, text: link.text })
+            })
+            .collect();
+        links.extend(markdown::wikilinks(markdown).into_iter()
+            .map(|link| Link::Internal { url: link.target, text: link.label }));
+        links
     }
 
-    #[test]
-    fn test_convert_path_to_url() {
-        assert_eq!(
-            super::convert_path_to_url(
-                "/wikidir",
-                "/lol/what/a/path.md"
-            ),
-            "/lol/what/a/path"
-        )
+    /// Returns the URLs of all pages within the wiki this page links to,
+    /// each URL once in the order of the first link to it. Relative links
+    /// are resolved against the URL of the page, extensions and fragments
+    /// are removed, so all links to a page result in the same URL
+    pub fn outbound_internal_links(&self) -> Vec<String> {
+        let mut urls: Vec<String> = Vec::new();
+        for link in self.links() {
+            let url = match link {
+                Link::Internal { url, .. } => resolve_link(self.url.as_str(), url.as_str()),
+                _ => None
+            };
+            if let Some(url) = url {
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }
+        }
+        urls
     }
 
-    #[test]
-    fn test_convert_url_to_path() {
-        assert_eq!(
-            super::convert_url_to_path(
-                "/wikidir",
-                "/lol/what/a/path",
-            ),
-            "/wikidir/lol/what/a/path.md"
-        )
+    /// Returns the table of contents of the page, which is the level, the
+    /// plain text and a unique anchor id for every heading of the rendered
+    /// markdown
+    pub fn toc(&self) -> Vec<(u8, String, String)> {
+        toc_entries(render::headings(self.render_base().as_str()))
     }
 
-    #[test]
-    fn test_get_page_empty() {
-        let wiki = make_tmp_wiki("empty_page");
-        match wiki.get_page("does_not_exist") {
-            Some(_) => panic!("We got a page although we should not have"),
-            None => ()
+    /// Returns the headings of the page as a tree, every heading contains
+    /// the following headings with a deeper level. Skipped levels are no
+    /// problem, a `###` following a `#` simply becomes its child
+    pub fn headings_outline(&self) -> Vec<OutlineNode> {
+        OutlineNode::build(&self.toc(), &mut 0, 0)
+    }
+
+    /// Returns the anchor id of every heading on the page, in the order of
+    /// the headings. These are the ids added to the headings of the HTML,
+    /// so links to `/page#anchor` can be checked against them
+    pub fn anchors(&self) -> Vec<String> {
+        markdown::unique_slugs(&render::headings(self.render_base().as_str()))
+    }
+
+    /// Returns a plain text excerpt of the page of at most `max_chars`
+    /// characters. This is the `description` of the frontmatter if there is
+    /// one, otherwise the first paragraph of the markdown
+    pub fn summary(&self, max_chars: usize) -> String {
+        let summary = match self.get_meta("description").and_then(yaml_to_string) {
+            Some(description) => description,
+            None => markdown::first_paragraph(self.markdown_raw.as_str())
+                .map(|paragraph| markdown::strip_inline(paragraph.as_str()))
+                .unwrap_or_default()
+        };
+        truncate_words(summary.trim(), max_chars)
+    }
+
+    /// Returns a summary of the metadata of the page
+    pub fn info(&self) -> PageInfo {
+        PageInfo {
+            url: self.url.clone(),
+            title: self.title(),
+            tags: self.tags(),
+            last_modified: self.last_modified().ok(),
+            summary: self.summary(INFO_SUMMARY_LENGTH),
         }
     }
+
+    /// Returns the markdown of the page as plain text without any markdown
+    /// syntax, i.e. for indexing. Links are replaced by their text
+    pub fn plain_text(&self) -> String {
+        markdown::plain_text(self.markdown_raw.as_str())
+    }
+
+    /// Returns the number of words in the markdown of the page, including
+    /// the contents of fenced code blocks
+    pub fn word_count(&self) -> usize {
+        markdown::word_count(self.markdown_raw.as_str(), true)
+    }
+
+    /// Same as `word_count`, but skips the contents of fenced code blocks
+    pub fn word_count_without_code(&self) -> usize {
+        markdown::word_count(self.markdown_raw.as_str(), false)
+    }
+
+    /// Returns an estimate of how long it takes to read the page at a speed
+    /// of 200 words per minute
+    pub fn reading_time(&self) -> Duration {
+        self.reading_time_at(DEFAULT_WORDS_PER_MINUTE)
+    }
+
+    /// Returns an estimate of how long it takes to read the page at the
+    /// given speed, code blocks are not taken into account
+    /// # Panics
+    /// This will panic if `words_per_minute` is zero
+    pub fn reading_time_at(&self, words_per_minute: usize) -> Duration {
+        assert!(words_per_minute > 0, "words per minute have to be positive");
+        let millis = self.word_count_without_code() * 60_000 / words_per_minute;
+        Duration::from_millis(millis as u64)
+    }
+
+}
+
+/// A page borrowed mutably from a wiki using `Wiki::get_page_mut`, it
+/// dereferences to the `Page`. Once it is dropped the wiki picks up the
+/// changes made to the page, i.e. to its aliases and wikilinks
+pub struct PageMut<'a> {
+    wiki: &'a mut Wiki,
+    index: usize,
+}
+
+impl<'a> Deref for PageMut<'a> {
+    type Target = Page;
+
+    fn deref(&self) -> &Page {
+        &self.wiki.pages[self.index]
+    }
+}
+
+impl<'a> DerefMut for PageMut<'a> {
+    fn deref_mut(&mut self) -> &mut Page {
+        &mut self.wiki.pages[self.index]
+    }
+}
+
+impl<'a> Drop for PageMut<'a> {
+    fn drop(&mut self) {
+        self.wiki.reindex_aliases();
+        self.wiki.update_missing_links();
+    }
+}
+
+/// An overview of the contents of a wiki, see `Wiki::stats`. With the
+/// `serde` feature enabled this can be serialized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WikiStats {
+    /// the number of pages, including drafts
+    pub pages: usize,
+    /// the number of words of all pages, see `Page::word_count`
+    pub words: usize,
+    /// the number of pages that are drafts
+    pub drafts: usize,
+    /// the number of distinct tags across all pages
+    pub tags: usize,
+    /// the number of links to pages that do not exist, see
+    /// `Wiki::broken_links`
+    pub broken_links: usize,
+}
+
+/// The order in which `Wiki::page_list` returns the pages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    /// sorted by the URL of the pages
+    Url,
+    /// sorted by the title of the pages, ignoring case
+    Title,
+    /// the most recently modified pages first
+    ModifiedDesc,
+}
+
+/// How `Wiki::get_page` matches URLs if there is no page with the exact URL
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UrlMatching {
+    /// only exact matches are returned
+    Exact,
+    /// fall back to a page whose URL only differs in case
+    CaseInsensitive,
+    /// like `CaseInsensitive`, but also fall back to the page whose URL is
+    /// closest to the given one, as long as at most the given number of
+    /// characters differ
+    Fuzzy(usize),
+}
+
+/// A wiki object
+pub struct Wiki {
+    /// the root path of the wiki
+    pub path: PathBuf,
+    /// the pages that are contained in this wiki
+    pub pages: Vec<Page>,
+    /// the options used to render the markdown of all pages
+    pub markdown_options: MarkdownOptions,
+    /// the file extensions of pages without the leading dot, new pages
+    /// are created using the first one
+    pub extensions: Vec<String>,
+    /// the name of the page that is used for the URL of a directory, i.e.
+    /// `/docs` refers to `/docs/index` if there is no `/docs` page
+    pub index_name: String,
+    /// the template used to render pages as complete HTML documents
+    pub template: Template,
+    /// how URLs are matched if there is no page with the exact URL
+    pub url_matching: UrlMatching,
+    /// whether drafts are included in listings, searches and exports
+    pub include_drafts: bool,
+    /// the format frontmatter of new pages is written in, existing pages
+    /// keep the format of their file
+    pub frontmatter_format: FrontmatterFormat,
+    /// whether exports write a page redirecting to the canonical page at
+    /// every alias
+    pub export_redirects: bool,
+    /// glob patterns of the files that are not loaded as pages, matched
+    /// against the path relative to the root like in a `.gitignore`, i.e.
+    /// `_*` or `node_modules/**`
+    pub exclude: Vec<String>,
+    /// how deep below the root files are loaded as pages, files directly
+    /// in the root are at depth 1. All files are loaded if this is `None`
+    pub max_depth: Option<usize>,
+    /// further directories whose pages are loaded into the same namespace
+    /// as those of `path`. If several roots contain a page with the same
+    /// URL, the page of the last root wins. New pages are always created
+    /// below `path` and only `path` is watched
+    pub roots: Vec<PathBuf>,
+    /// the URL of the entry point of the wiki, which is never an orphan.
+    /// Defaults to `/`, the index page of the root directory
+    pub home_page: String,
+    /// watches the directory for changes once `watch` has been called
+    watcher: Option<watch::Watcher>,
+    /// maps the url of every page to its position in `pages`
+    index: HashMap<String, usize>,
+    /// maps the aliases of the pages to their position in `pages`, the
+    /// urls in `index` take precedence
+    aliases: HashMap<String, usize>,
+}
+
+impl Wiki {
+    /// Creates a new `Wiki` object. Will automatically load all pages
+    /// that are contained
+    /// # Errors
+    /// This will return an error if the root directory of the wiki can not
+    /// be read, i.e. because it does not exist
+    pub fn new(pathname: &str) -> Result<Wiki, WikiError> {
+        Wiki::builder().path(pathname).build()
+    }
+
+    /// Same as `new`, but returns a future for use within a tokio runtime.
+    /// Loading reads and parses files, so it runs on the blocking thread
+    /// pool instead of stalling the runtime. The resulting wiki is the same
+    /// as the one returned by `new`
+    /// # Errors
+    /// See `new`
+    /// # Panics
+    /// The future panics if it is polled outside of a tokio runtime
+    #[cfg(feature = "tokio")]
+    pub fn new_async(pathname: &str) -> LoadWiki {
+        LoadWiki::new(pathname)
+    }
+
+    /// Returns a builder to configure the wiki before loading it
+    pub fn builder() -> WikiBuilder {
+        WikiBuilder::default()
+    }
+
+    /// Creates a wiki from pages that have been built in memory, i.e. using
+    /// `Page::new`, without reading the directory at the given path. Pages
+    /// created or saved later on are still written below that path
+    /// # Errors
+    /// This will return `WikiError::InvalidUrl` if a page does not belong
+    /// to the directory or `WikiError::AlreadyExists` if several pages
+    /// share a URL
+    pub fn from_pages(path: PathBuf, pages: Vec<Page>) -> Result<Wiki, WikiError> {
+        Wiki::builder().path(path).build_from_pages(pages)
+    }
+
+    /// Creates a wiki serving the pages of several directories as one, i.e.
+    /// a directory of core documentation and one of a plugin. If several
+    /// directories contain a page with the same URL, the page of the later
+    /// directory is used and a warning is printed. New pages are created in
+    /// the first directory
+    /// # Errors
+    /// This will return `WikiError::NotFound` if no directory is given or
+    /// an error if one of the directories can not be read
+    pub fn new_multi(paths: &[&str]) -> Result<Wiki, WikiError> {
+        match paths.split_first() {
+            Some((path, roots)) => Wiki::builder().path(path).roots(roots).build(),
+            None => Err(WikiError::NotFound(String::new()))
+        }
+    }
+
+    /// Same as `new`, but renders the markdown of all pages using the given
+    /// options
+    /// # Errors
+    /// See `new`
+    pub fn with_markdown_options(pathname: &str, markdown_options: MarkdownOptions)
+                                 -> Result<Wiki, WikiError> {
+        Wiki::builder().path(pathname).markdown_options(markdown_options).build()
+    }
+
+    /// Same as `new`, but only loads files having one of the given
+    /// extensions as pages, i.e. `["md", "markdown"]`
+    /// # Errors
+    /// See `new`
+    pub fn with_extensions(pathname: &str, extensions: &[&str]) -> Result<Wiki, WikiError> {
+        Wiki::builder().path(pathname).extensions(extensions).build()
+    }
+
+    /// Load all the pages in the wiki, the files are read in parallel and
+    /// the pages are sorted by their URL afterwards. Pages that are already
+    /// loaded are kept including their rendered HTML if their file has not
+    /// changed
+    /// # Errors
+    /// This will return an error if the root directory can not be read,
+    /// unreadable entries below it are skipped with a warning
+    fn load_pages(&mut self) -> Result<(), WikiError> {
+        // make sure we do not duplicate shit by taking the pages out of the
+        // vector first, keeping those rendered with the current options
+        let markdown_options = &self.markdown_options;
+        let mut loaded: HashMap<PathBuf, Page> = self.pages.drain(..)
+            .filter(|page| page.markdown_options == *markdown_options)
+            .map(|page| (page.path.clone(), page))
+            .collect();
+
+        let roots: Vec<PathBuf> = Some(self.path.clone()).into_iter()
+            .chain(self.roots.iter().cloned())
+            .collect();
+        let mut paths = Vec::new();
+        let mut path_roots = Vec::new();
+        for root in roots.iter() {
+            for path in self.find_page_files(root)? {
+                paths.push(path);
+                path_roots.push(root.as_path());
+            }
+        }
+
+        let sources = PageSource::read_all(&paths, |path| {
+            PageSource::reread(path, loaded.get(path).and_then(Page::loaded_hash))
+        });
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for ((path, root), source) in paths.into_iter().zip(path_roots).zip(sources) {
+            let url = try_convert_path_to_url(root, path.as_path(), &self.extensions);
+            let page = url.and_then(|url| source.map(|source| match source {
+                Reread::Unchanged(modified) => {
+                    let mut page = loaded.remove(&path).expect("unchanged pages are loaded");
+                    page.url = url;
+                    page.modified = modified;
+                    page
+                }
+                Reread::Changed(source) => Page::from_source(
+                    root.to_path_buf(),
+                    path.clone(),
+                    url,
+                    source,
+                    self.markdown_options.clone(),
+                    self.frontmatter_format
+                )
+            }));
+            let page = match page {
+                Ok(page) => page,
+                Err(e) => {
+                    println!("Failed loading {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            // the pages of later roots win
+            match positions.get(page.url.as_str()) {
+                Some(&i) => {
+                    println!("{} overrides {} at {}", path.display(),
+                             self.pages[i].path.display(), page.url);
+                    self.pages[i] = page;
+                }
+                None => {
+                    positions.insert(page.url.clone(), self.pages.len());
+                    self.pages.push(page);
+                }
+            }
+        }
+        self.pages.sort_by(|a, b| a.url.cmp(&b.url));
+        self.reindex();
+        self.update_missing_links();
+        Ok(())
+    }
+
+    /// Returns the paths of the files below the given root that are loaded
+    /// as pages
+    /// # Errors
+    /// This will return an error if the root can not be read
+    fn find_page_files(&self, root: &Path) -> Result<Vec<PathBuf>, WikiError> {
+        let mut paths = Vec::new();
+        let walk = match self.max_depth {
+            Some(max_depth) => WalkDir::new(root).max_depth(max_depth),
+            None => WalkDir::new(root)
+        };
+        for entry in walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    if e.depth() == 0 {
+                        return Err(WikiError::from(io::Error::from(e)));
+                    }
+                    println!("Skipping unreadable entry: {}", e);
+                    continue;
+                }
+            };
+            let entry = entry.path();
+            let entry_path_str = match entry.to_str() {
+                Some(path_str) => path_str,
+                None => {
+                    println!("Skipping non utf-8 path: {}", entry.display());
+                    continue;
+                }
+            };
+            if entry.is_file() && page_extension(entry_path_str, &self.extensions).is_some()
+                && !self.is_excluded(root, entry) {
+                paths.push(entry.to_path_buf());
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Reloads all pages from disk, picking up any changes made to the files
+    /// since the wiki was loaded and dropping pages whose files are gone
+    /// # Errors
+    /// This will return an error if the root directory can not be read
+    pub fn reload(&mut self) -> Result<(), WikiError> {
+        self.load_pages()
+    }
+
+    /// Renders the HTML of every page again using the current
+    /// `markdown_options` of the wiki, call this after changing them. The
+    /// pages are not modified, so nothing needs to be saved afterwards
+    pub fn render_all(&mut self) {
+        for page in self.pages.iter_mut() {
+            page.markdown_options = self.markdown_options.clone();
+            page.html = OnceLock::new();
+        }
+        // `mark_missing_links` may have been toggled
+        self.update_missing_links();
+        for page in &self.pages {
+            page.html();
+        }
+    }
+
+    /// Marks wikilinks to pages that do not exist if enabled in the
+    /// markdown options and clears the marks otherwise, needs to be called
+    /// whenever pages are added or removed
+    fn update_missing_links(&mut self) {
+        // targets are resolved like `get_page` does, including index pages
+        let missing_links: Vec<Vec<String>> = self.pages.iter().map(|page| {
+            if !page.markdown_options.mark_missing_links {
+                return Vec::new();
+            }
+            markdown::wikilinks(page.markdown_raw.as_str())
+                .into_iter()
+                .map(|link| link.target)
+                .filter(|url| self.position(url).is_none())
+                .collect()
+        }).collect();
+        for (page, missing_links) in self.pages.iter_mut().zip(missing_links) {
+            page.set_missing_links(missing_links);
+        }
+    }
+
+    /// Rebuilds the url index from scratch, needs to be called whenever
+    /// pages are removed or reordered
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (i, page) in self.pages.iter().enumerate() {
+            self.index.insert(page.url.clone(), i);
+        }
+        self.reindex_aliases();
+    }
+
+    /// Rebuilds the alias index from scratch, needs to be called whenever
+    /// pages are added or their frontmatter is reloaded. If several pages
+    /// share an alias, the first one by URL wins
+    fn reindex_aliases(&mut self) {
+        self.aliases.clear();
+        for (i, page) in self.pages.iter().enumerate() {
+            for alias in page.aliases() {
+                self.aliases.entry(alias).or_insert(i);
+            }
+        }
+    }
+
+    /// Returns the number of pages in the wiki, including drafts
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Returns whether the wiki does not contain any pages
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Returns an iterator over all pages in the wiki
+    pub fn iter(&self) -> slice::Iter<'_, Page> {
+        self.pages.iter()
+    }
+
+    /// Returns an iterator over the pages that are included in listings,
+    /// this leaves out drafts unless `include_drafts` is set
+    fn listed_pages(&self) -> impl Iterator<Item = &Page> {
+        let include_drafts = self.include_drafts;
+        self.pages.iter().filter(move |page| include_drafts || !page.is_draft())
+    }
+
+    /// Returns the URLs of all pages in the wiki in sorted order
+    pub fn urls(&self) -> Vec<&str> {
+        let mut urls: Vec<&str> = self.listed_pages().map(|page| page.url.as_str()).collect();
+        urls.sort();
+        urls
+    }
+
+    /// Returns an XML sitemap listing the pages of the wiki below the given
+    /// base URL, i.e. `https://example.com/wiki`, along with the time their
+    /// files were modified
+    pub fn sitemap_xml(&self, base_url: &str) -> String {
+        let entries: Vec<(String, Option<SystemTime>)> = self.listed_pages()
+            .map(|page| (
+                feed::join_url(base_url, page.url.as_str()),
+                page.last_modified().ok()
+            ))
+            .collect();
+        feed::sitemap(&entries)
+    }
+
+    /// Returns an RSS feed of the `limit` most recently modified pages,
+    /// newest first, using the given base URL for the links
+    pub fn rss_feed(&self, base_url: &str, limit: usize) -> String {
+        let mut pages: Vec<(Option<SystemTime>, &Page)> = self.listed_pages()
+            .map(|page| (page.last_modified().ok(), page))
+            .collect();
+        pages.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.url.cmp(&b.1.url)));
+        let items: Vec<feed::FeedItem> = pages.into_iter()
+            .take(limit)
+            .map(|(modified, page)| feed::FeedItem {
+                title: page.title(),
+                link: feed::join_url(base_url, page.url.as_str()),
+                description: String::from(page.html()),
+                published: modified,
+            })
+            .collect();
+        feed::rss("Recent changes", base_url, &items)
+    }
+
+    /// Returns up to `limit` pages starting at `offset` after sorting them
+    /// by the given key, i.e. for paginating a listing of all pages. Pages
+    /// with the same sort key are ordered by their URL
+    pub fn page_list(&self, offset: usize, limit: usize, sort: SortKey) -> Vec<&Page> {
+        let mut pages: Vec<&Page> = self.listed_pages().collect();
+        match sort {
+            SortKey::Url => pages.sort_by(|a, b| a.url.cmp(&b.url)),
+            SortKey::Title => pages.sort_by_cached_key(|page| {
+                (page.title().to_lowercase(), page.url.clone())
+            }),
+            SortKey::ModifiedDesc => pages.sort_by_cached_key(|page| {
+                (cmp::Reverse(page.last_modified().ok()), page.url.clone())
+            }),
+        }
+        pages.into_iter().skip(offset).take(limit).collect()
+    }
+
+    /// Returns the pages sorted by their publishing date, newest first.
+    /// Pages without a valid date come last, ordered by their URL
+    pub fn pages_sorted_by_date(&self) -> Vec<&Page> {
+        let mut pages: Vec<(Option<NaiveDate>, &Page)> = self.listed_pages()
+            .map(|page| (page.date(), page))
+            .collect();
+        pages.sort_by(|a, b| {
+            let by_date = match (a.0, b.0) {
+                (Some(a), Some(b)) => b.cmp(&a),
+                (Some(_), None) => cmp::Ordering::Less,
+                (None, Some(_)) => cmp::Ordering::Greater,
+                (None, None) => cmp::Ordering::Equal
+            };
+            by_date.then_with(|| a.1.url.cmp(&b.1.url))
+        });
+        pages.into_iter().map(|(_, page)| page).collect()
+    }
+
+    /// Returns the pages published before the given date, newest first.
+    /// Pages published on the date itself and pages without a valid date
+    /// are left out
+    pub fn pages_published_before(&self, date: NaiveDate) -> Vec<&Page> {
+        self.pages_sorted_by_date().into_iter()
+            .filter(|page| page.date().is_some_and(|published| published < date))
+            .collect()
+    }
+
+    /// Returns an overview of the contents of the wiki, drafts are included
+    pub fn stats(&self) -> WikiStats {
+        let tags: HashSet<String> = self.pages.iter().flat_map(|page| page.tags()).collect();
+        WikiStats {
+            pages: self.len(),
+            words: self.pages.iter().map(|page| page.word_count()).sum(),
+            drafts: self.pages.iter().filter(|page| page.is_draft()).count(),
+            tags: tags.len(),
+            broken_links: self.broken_links().len(),
+        }
+    }
+
+    /// Returns the pages organized into a tree by the segments of their
+    /// URLs, directories without a page of their own are included as nodes
+    /// without a page
+    pub fn tree(&self) -> TreeNode<'_> {
+        tree::build(self.listed_pages())
+    }
+
+    /// Returns the position of the page with the given URL in `pages`,
+    /// falling back to the index page of the directory with that URL
+    fn position(&self, url: &str) -> Option<usize> {
+        let url = normalize_url(url);
+        self.index.get(url.as_str())
+            .or_else(|| self.aliases.get(url.as_str()))
+            .or_else(|| {
+                let index_url = format!("{}/{}", url.trim_end_matches('/'), self.index_name);
+                self.index.get(index_url.as_str())
+            })
+            .cloned()
+    }
+
+    /// Same as `position`, but falls back to inexact matches of the URL as
+    /// configured by `url_matching`
+    fn find_position(&self, url: &str) -> Option<usize> {
+        if let Some(position) = self.position(url) {
+            return Some(position);
+        }
+        let max_distance = match self.url_matching {
+            UrlMatching::Exact => return None,
+            UrlMatching::CaseInsensitive => 0,
+            UrlMatching::Fuzzy(max_distance) => max_distance
+        };
+        let url = normalize_url(url).to_lowercase();
+        self.pages.iter()
+            .enumerate()
+            .map(|(i, page)| (levenshtein(url.as_str(), page.url.to_lowercase().as_str()), i))
+            .filter(|&(distance, _)| distance <= max_distance)
+            .min_by(|a, b| a.0.cmp(&b.0).then_with(|| self.pages[a.1].url.cmp(&self.pages[b.1].url)))
+            .map(|(_, i)| i)
+    }
+
+    /// Returns whether there is a page with the given URL, this is the same
+    /// as `get_page(url).is_some()` and looks the URL up the same way
+    pub fn contains(&self, url: &str) -> bool {
+        self.find_position(url).is_some()
+    }
+
+    /// Will get an individual page object given a URL, which is normalized
+    /// first so `page/` and `//page` both refer to `/page`. If there is no
+    /// page with that exact URL, the index page of the directory is
+    /// returned. Inexact matches are only returned if enabled through
+    /// `url_matching`
+    pub fn get_page(&self, url: &str) -> Option<&Page> {
+        match self.find_position(url) {
+            Some(i) => self.pages.get(i),
+            None => None
+        }
+    }
+
+    /// Returns all pages carrying the given tag, sorted by their URL
+    pub fn pages_by_tag(&self, tag: &str) -> Vec<&Page> {
+        let mut pages: Vec<&Page> = self.listed_pages()
+            .filter(|page| page.tags().iter().any(|t| t == tag))
+            .collect();
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+        pages
+    }
+
+    /// Returns the distinct tags of all pages that start with the given
+    /// prefix ignoring case, i.e. to suggest tags while they are typed. Tags
+    /// only differing in case are returned once, spelled like on the first
+    /// page using them, and the tags are sorted ignoring case
+    pub fn search_tags(&self, prefix: &str) -> Vec<String> {
+        let prefix = prefix.to_lowercase();
+        let mut seen = HashSet::new();
+        let mut tags: Vec<(String, String)> = self.listed_pages()
+            .flat_map(|page| page.tags())
+            .map(|tag| (tag.to_lowercase(), tag))
+            .filter(|(key, _)| key.starts_with(prefix.as_str()) && seen.insert(key.clone()))
+            .collect();
+        tags.sort();
+        tags.into_iter().map(|(_, tag)| tag).collect()
+    }
+
+    /// Returns up to `limit` other pages sharing tags with the page at the
+    /// given URL, the pages sharing the most tags come first and pages
+    /// sharing as many tags are sorted by their title. Pages sharing no
+    /// tags are left out, as is everything if there is no such page
+    pub fn related_pages(&self, url: &str, limit: usize) -> Vec<&Page> {
+        let page = match self.get_page(url) {
+            Some(page) => page,
+            None => return Vec::new()
+        };
+        let tags: HashSet<String> = page.tags().into_iter().collect();
+        let mut related: Vec<(usize, String, &Page)> = self.listed_pages()
+            .filter(|other| other.url != page.url)
+            .filter_map(|other| {
+                let other_tags: HashSet<String> = other.tags().into_iter().collect();
+                match other_tags.intersection(&tags).count() {
+                    0 => None,
+                    shared => Some((shared, other.title(), other))
+                }
+            })
+            .collect();
+        related.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        related.into_iter().take(limit).map(|(_, _, page)| page).collect()
+    }
+
+    /// Searches the title and markdown body of all pages for the given
+    /// phrase, ignoring case. The matching pages are ordered by the number
+    /// of matches, the page with the most matches comes first
+    pub fn search(&self, query: &str) -> Vec<&Page> {
+        let query = query.to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut results: Vec<(usize, &Page)> = self.listed_pages()
+            .map(|page| {
+                let matches = page.title().to_lowercase().matches(query.as_str()).count()
+                    + page.plain_text().to_lowercase().matches(query.as_str()).count();
+                (matches, page)
+            })
+            .filter(|&(matches, _)| matches > 0)
+            .collect();
+        results.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.url.cmp(&b.1.url)));
+        results.into_iter().map(|(_, page)| page).collect()
+    }
+
+    /// Returns all other pages that link to the page with the given URL,
+    /// sorted by their URL. The URL and the link targets are resolved like
+    /// `get_page` does, so links to an alias or to the directory of an
+    /// index page count as well
+    pub fn backlinks(&self, url: &str) -> Vec<&Page> {
+        let target = match self.position(url) {
+            Some(target) => target,
+            None => return Vec::new()
+        };
+        let mut pages: Vec<&Page> = self.pages.iter()
+            .enumerate()
+            .filter(|&(i, page)| i != target && page.outbound_internal_links().iter()
+                .any(|linked| self.position(linked.as_str()) == Some(target)))
+            .map(|(_, page)| page)
+            .collect();
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+        pages
+    }
+
+    /// Returns all pages no other page links to, sorted by their URL. Links
+    /// of a page to itself do not count and the `home_page` is never an
+    /// orphan, as it is the entry point of the wiki
+    pub fn orphaned_pages(&self) -> Vec<&Page> {
+        let mut linked = vec![false; self.pages.len()];
+        for (i, page) in self.pages.iter().enumerate() {
+            for target in page.outbound_internal_links() {
+                match self.position(target.as_str()) {
+                    Some(position) if position != i => linked[position] = true,
+                    _ => ()
+                }
+            }
+        }
+        let home = self.position(self.home_page.as_str());
+        let mut pages: Vec<&Page> = self.pages.iter()
+            .enumerate()
+            .filter(|&(i, _)| !linked[i] && Some(i) != home)
+            .map(|(_, page)| page)
+            .collect();
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+        pages
+    }
+
+    /// Returns a `(page_url, target)` pair for every link within the wiki
+    /// whose target does not exist, ordered by the page URL. Both markdown
+    /// links and wikilinks are checked, external links are ignored
+    pub fn broken_links(&self) -> Vec<(String, String)> {
+        let mut broken = Vec::new();
+        for page in self.pages.iter() {
+            let mut missing: Vec<String> = Vec::new();
+            for target in page.outbound_internal_links() {
+                if self.position(target.as_str()).is_none() && !missing.contains(&target) {
+                    missing.push(target);
+                }
+            }
+            broken.extend(missing.into_iter().map(|target| (page.url.clone(), target)));
+        }
+        broken.sort_by(|a, b| a.0.cmp(&b.0));
+        broken
+    }
+
+    /// Checks the frontmatter of every page using `validate_frontmatter`
+    /// and returns a `(page_url, missing_keys)` pair for every page that
+    /// lacks any of the required keys, ordered by the page URL
+    pub fn validate_all(&self, required: &[&str]) -> Vec<(String, Vec<String>)> {
+        let mut invalid: Vec<(String, Vec<String>)> = self.pages.iter()
+            .filter_map(|page| page.validate_frontmatter(required).err()
+                .map(|missing| (page.url.clone(), missing)))
+            .collect();
+        invalid.sort_by(|a, b| a.0.cmp(&b.0));
+        invalid
+    }
+
+    /// Returns the page backed by the file at the given path, which may also
+    /// be the canonical path of the file as reported by file watchers
+    pub fn get_by_path(&self, path: &Path) -> Option<&Page> {
+        if let Some(page) = self.pages.iter().find(|page| page.path == path) {
+            return Some(page);
+        }
+        let (path, _) = self.watched_page(path)?;
+        self.pages.iter().find(|page| page.path == path)
+    }
+
+    /// Will get an individual page object given a URL that can be modified,
+    /// i.e. to update its markdown and save it back to disk. Falls back to
+    /// the index page of the directory just like `get_page`. The aliases and
+    /// missing links of the wiki are updated once the `PageMut` is dropped
+    pub fn get_page_mut(&mut self, url: &str) -> Option<PageMut<'_>> {
+        let index = self.find_position(url)?;
+        Some(PageMut { wiki: self, index })
+    }
+
+    /// Will create a new page within this wiki using the given URL
+    /// It will not be persisted to disk until you call `Page::save_to_file`
+    /// and not be part of the wiki until you call `Wiki::add_page`
+    /// # Errors
+    /// This will return `WikiError::InvalidUrl` if the URL points outside
+    /// of the directory of the wiki, i.e. `/../secret`, or the path of the
+    /// directory is not valid utf-8
+    pub fn new_page(&self, url: &str) -> Result<Page, WikiError> {
+        let extension = match self.extensions.first() {
+            Some(extension) => extension.as_str(),
+            None => DEFAULT_EXTENSION
+        };
+        self.new_page_with_extension(self.path.as_path(), url, extension)
+    }
+
+    /// Same as `new_page`, but places the backing file below the given root
+    /// of the wiki and uses the given file extension for it
+    fn new_page_with_extension(&self, root: &Path, url: &str, extension: &str)
+                               -> Result<Page, WikiError> {
+        let base_path = match root.to_str() {
+            Some(base_path) => base_path,
+            None => return Err(WikiError::InvalidUrl(root.to_string_lossy().into_owned()))
+        };
+        let path = convert_url_to_path(base_path, url, extension)?;
+        let url = convert_path_to_url(base_path, path.as_str(), &[extension]);
+        Ok(Page::empty(root.to_path_buf(), PathBuf::from(path), url,
+                       self.markdown_options.clone(), self.frontmatter_format))
+    }
+
+    /// Will add an already constructed page to this wiki, i.e. one built
+    /// by `Wiki::new_page`. The page is not persisted to disk
+    /// # Errors
+    /// This will return an error if the page does not belong to the
+    /// directory of this wiki or if there already is a page with its URL
+    pub fn add_page(&mut self, page: Page) -> Result<&Page, WikiError> {
+        if page.base_path != self.path {
+            return Err(WikiError::InvalidUrl(page.url));
+        }
+        if self.index.contains_key(page.url.as_str()) {
+            return Err(WikiError::AlreadyExists(page.url));
+        }
+        let index = self.insert_sorted(page);
+        self.update_missing_links();
+        Ok(&self.pages[index])
+    }
+
+    /// Inserts the page at the position keeping `pages` sorted by URL and
+    /// returns that position
+    fn insert_sorted(&mut self, page: Page) -> usize {
+        let index = self.pages.binary_search_by(|other| other.url.cmp(&page.url))
+            .unwrap_or_else(|index| index);
+        self.pages.insert(index, page);
+        self.reindex();
+        index
+    }
+
+    /// Will create a new page within this wiki using the given URL and
+    /// immediately persist it to disk, creating any missing directories
+    /// # Errors
+    /// This will return an error if a page with the given URL already
+    /// exists or if writing the file fails
+    pub fn create_page(&mut self, url: &str, markdown: &str)
+                       -> Result<&Page, WikiError> {
+        let mut page = self.new_page(url)?;
+        if self.index.contains_key(page.url.as_str()) || page.path.exists() {
+            return Err(WikiError::AlreadyExists(String::from(url)));
+        }
+        if let Some(parent) = page.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        page.update_markdown(markdown);
+        page.save_to_file()?;
+        let index = self.insert_sorted(page);
+        self.update_missing_links();
+        Ok(&self.pages[index])
+    }
+
+    /// Will delete the page with the given URL, removing both the backing
+    /// file and the page object
+    /// # Errors
+    /// This will return `WikiError::NotFound` if there is no page with the
+    /// given URL or an io error if removing the file fails
+    pub fn delete_page(&mut self, url: &str) -> Result<(), WikiError> {
+        let index = match self.index.get(normalize_url(url).as_str()) {
+            Some(&index) => index,
+            None => return Err(WikiError::NotFound(String::from(url)))
+        };
+        fs::remove_file(self.pages[index].path.as_path())?;
+        self.pages.remove(index);
+        self.reindex();
+        self.update_missing_links();
+        Ok(())
+    }
+
+    /// Will move the page with the given URL to a new URL, renaming the
+    /// backing file and creating any missing directories
+    /// # Errors
+    /// This will return `WikiError::NotFound` if there is no page with the
+    /// old URL, `WikiError::AlreadyExists` if there already is a page with
+    /// the new URL or an io error if renaming the file fails
+    pub fn rename_page(&mut self, old_url: &str, new_url: &str)
+                       -> Result<(), WikiError> {
+        let index = match self.index.get(normalize_url(old_url).as_str()) {
+            Some(&index) => index,
+            None => return Err(WikiError::NotFound(String::from(old_url)))
+        };
+        // keep the extension of the backing file
+        let extension = self.pages[index].path.to_str()
+            .and_then(|path| page_extension(path, &self.extensions))
+            .unwrap_or(DEFAULT_EXTENSION)
+            .to_string();
+        // the page stays within the root of the wiki it has been loaded from
+        let root = self.pages[index].base_path.clone();
+        let new_page = self.new_page_with_extension(root.as_path(), new_url, extension.as_str())?;
+        if self.index.contains_key(new_page.url.as_str()) || new_page.path.exists() {
+            return Err(WikiError::AlreadyExists(String::from(new_url)));
+        }
+        if let Some(parent) = new_page.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(self.pages[index].path.as_path(), new_page.path.as_path())?;
+        let mut page = self.pages.remove(index);
+        page.path = new_page.path;
+        page.url = new_page.url;
+        self.insert_sorted(page);
+        self.update_missing_links();
+        Ok(())
+    }
+
+    /// Moves the page like `rename_page` and points all links to the old
+    /// URL at the new one, covering markdown links, reference definitions
+    /// and wikilinks. Relative links are replaced by the absolute new URL.
+    /// The pages that have been changed are saved, the number of links that
+    /// have been rewritten is returned
+    /// # Errors
+    /// This will return the errors of `rename_page`, in which case no links
+    /// are changed, or a `WikiError::Save` carrying the URL of the first
+    /// page that can not be saved
+    pub fn rename_page_and_rewrite_links(&mut self, old_url: &str, new_url: &str)
+                                         -> Result<usize, WikiError> {
+        let old_url = normalize_url(old_url);
+        if !self.index.contains_key(old_url.as_str()) {
+            return Err(WikiError::NotFound(old_url));
+        }
+        self.rename_page(old_url.as_str(), new_url)?;
+        // the page has moved within `pages` to stay sorted by URL
+        let index = self.index[normalize_url(new_url).as_str()];
+        let new_url = self.pages[index].url.clone();
+
+        let mut rewritten = 0;
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            // links on the moved page are relative to where it used to be
+            let base = if i == index { old_url.clone() } else { page.url.clone() };
+            let (markdown, count) = markdown::rewrite_link_targets(
+                page.markdown_raw.as_str(),
+                |target| {
+                    if resolve_link(base.as_str(), target).as_ref() != Some(&old_url) {
+                        return None;
+                    }
+                    let suffix = target.find(['#', '?']).map_or("", |i| &target[i..]);
+                    let url = if target.contains(' ') {
+                        new_url.clone()
+                    } else {
+                        new_url.replace(' ', "%20")
+                    };
+                    Some(format!("{}{}", url, suffix))
+                }
+            );
+            if count == 0 {
+                continue;
+            }
+            page.update_markdown(markdown.as_str());
+            page.save_to_file()
+                .map_err(|e| WikiError::Save(page.url.clone(), Box::new(e)))?;
+            rewritten += count;
+        }
+        self.update_missing_links();
+        Ok(rewritten)
+    }
+
+    /// Moves every page at or below the URL `old_prefix` below `new_prefix`,
+    /// i.e. `/old/notes` becomes `/new/notes`, renaming the backing files
+    /// and removing directories that are left empty. Returns the number of
+    /// pages that have been moved
+    /// # Errors
+    /// This will return `WikiError::InvalidUrl` if one of the prefixes
+    /// contains the other, `WikiError::AlreadyExists` if any of the new URLs
+    /// is taken, in both cases nothing is moved, or an io error if renaming
+    /// a file fails, in which case the pages moved so far stay moved
+    pub fn move_subtree(&mut self, old_prefix: &str, new_prefix: &str)
+                        -> Result<usize, WikiError> {
+        let old_prefix = normalize_url(old_prefix);
+        let new_prefix = normalize_url(new_prefix);
+        let is_below = |url: &str, prefix: &str| {
+            url == prefix || prefix == "/" || url.starts_with(format!("{}/", prefix).as_str())
+        };
+        if is_below(&new_prefix, &old_prefix) || is_below(&old_prefix, &new_prefix) {
+            return Err(WikiError::InvalidUrl(new_prefix));
+        }
+
+        let mut moves = Vec::new();
+        for page in self.pages.iter().filter(|page| is_below(&page.url, &old_prefix)) {
+            let new_url = format!("{}{}", new_prefix.trim_end_matches('/'),
+                                  &page.url[old_prefix.len()..]);
+            let extension = page.path.to_str()
+                .and_then(|path| page_extension(path, &self.extensions))
+                .unwrap_or(DEFAULT_EXTENSION);
+            let new_page = self.new_page_with_extension(page.base_path.as_path(),
+                                                        new_url.as_str(), extension)?;
+            if self.index.contains_key(new_page.url.as_str()) || new_page.path.exists() {
+                return Err(WikiError::AlreadyExists(new_page.url));
+            }
+            moves.push((page.url.clone(), new_page.url, page.path.clone(), page.base_path.clone()));
+        }
+
+        for (old_url, new_url, _, _) in &moves {
+            self.rename_page(old_url, new_url)?;
+        }
+        // deepest directories first, so parents are empty once they are reached
+        let mut directories: Vec<&Path> = moves.iter()
+            .flat_map(|(_, _, path, root)| {
+                path.ancestors().skip(1).filter(move |directory| {
+                    directory.starts_with(root) && directory != root
+                })
+            })
+            .collect();
+        directories.sort_by_key(|directory| cmp::Reverse(directory.components().count()));
+        directories.dedup();
+        for directory in directories {
+            // fails for directories that still contain other files
+            let _ = fs::remove_dir(directory);
+        }
+        Ok(moves.len())
+    }
+
+    /// Writes all pages that have been modified since they were loaded or
+    /// last saved to disk and returns how many pages have been written
+    /// # Errors
+    /// This will stop at the first page that can not be saved and return a
+    /// `WikiError::Save` carrying the URL of that page
+    pub fn save_all(&mut self) -> Result<usize, WikiError> {
+        let mut saved = 0;
+        for page in self.pages.iter_mut().filter(|page| page.is_dirty()) {
+            page.save_to_file()
+                .map_err(|e| WikiError::Save(page.url.clone(), Box::new(e)))?;
+            saved += 1;
+        }
+        Ok(saved)
+    }
+
+    /// Starts watching the directory of the wiki for changes made to its
+    /// files. The pages are only updated by `apply_changes` and
+    /// `wait_for_changes`, which return an event for every changed page
+    /// # Errors
+    /// This will return `WikiError::Watch` if the directory can not be
+    /// watched
+    pub fn watch(&mut self) -> Result<(), WikiError> {
+        self.watcher = Some(watch::Watcher::start(self.path.as_path())?);
+        Ok(())
+    }
+
+    /// Applies the changes to the files picked up since `watch` has been
+    /// called, new pages are loaded, changed pages are reloaded and pages
+    /// whose files are gone are removed. Pages with unsaved modifications
+    /// are not reloaded to not lose those. Returns the events for the
+    /// pages that have been changed, nothing if the wiki is not watched
+    pub fn apply_changes(&mut self) -> Vec<WikiEvent> {
+        let changes = match self.watcher {
+            Some(ref mut watcher) => watcher.take_changes(),
+            None => return Vec::new()
+        };
+        self.apply(changes)
+    }
+
+    /// Waits until pages have been changed on disk or the timeout has
+    /// passed and applies the changes like `apply_changes`. Returns the
+    /// events for the pages that have been changed, nothing if the timeout
+    /// has passed first or the wiki is not watched
+    pub fn wait_for_changes(&mut self, timeout: Duration) -> Vec<WikiEvent> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let changes = match self.watcher {
+                Some(ref mut watcher) => watcher.wait_for_changes(deadline),
+                None => return Vec::new()
+            };
+            // changes to files that are not pages do not produce events
+            let events = self.apply(changes);
+            if !events.is_empty() || Instant::now() >= deadline {
+                return events;
+            }
+        }
+    }
+
+    /// Applies the changes picked up by the watcher to the pages and
+    /// returns the events for them
+    fn apply(&mut self, changes: Vec<watch::Change>) -> Vec<WikiEvent> {
+        let mut events = Vec::new();
+        for change in changes {
+            match change {
+                watch::Change::Path(path) => {
+                    if path.is_dir() {
+                        // files might have been created before the new
+                        // directory has been watched
+                        let files: Vec<PathBuf> = WalkDir::new(&path).into_iter()
+                            .filter_map(|entry| entry.ok())
+                            .filter(|entry| entry.file_type().is_file())
+                            .map(|entry| entry.path().to_path_buf())
+                            .collect();
+                        for file in files {
+                            events.extend(self.apply_change(file.as_path()));
+                        }
+                    } else {
+                        events.extend(self.apply_change(path.as_path()));
+                    }
+                }
+                watch::Change::Rename(from, to) => events.extend(self.apply_rename(&from, &to)),
+            }
+        }
+        // a page that has just been created does not need to be reported
+        // as modified as well
+        let created: Vec<String> = events.iter()
+            .filter_map(|event| match *event {
+                WikiEvent::Created(ref url) => Some(url.clone()),
+                _ => None
+            })
+            .collect();
+        let mut unique: Vec<WikiEvent> = Vec::with_capacity(events.len());
+        for event in events {
+            let duplicate = match event {
+                WikiEvent::Modified(ref url) => created.contains(url),
+                _ => false
+            } || unique.contains(&event);
+            if !duplicate {
+                unique.push(event);
+            }
+        }
+        if !unique.is_empty() {
+            self.reindex_aliases();
+            self.update_missing_links();
+        }
+        unique
+    }
+
+    /// Returns the path below the wiki directory and the URL of the page
+    /// for a path reported by the watcher, `None` if it is not a page
+    fn watched_page(&self, path: &Path) -> Option<(PathBuf, String)> {
+        let path = if path.starts_with(&self.path) {
+            path.to_path_buf()
+        } else {
+            // the watcher might report canonical paths
+            let base = fs::canonicalize(&self.path).ok()?;
+            self.path.join(path.strip_prefix(base).ok()?)
+        };
+        page_extension(path.to_str()?, &self.extensions)?;
+        if self.is_excluded(self.path.as_path(), path.as_path()) {
+            return None;
+        }
+        let url = try_convert_path_to_url(self.path.as_path(), path.as_path(),
+                                          &self.extensions).ok()?;
+        Some((path, url))
+    }
+
+    /// Returns whether the file at the given path below the given root
+    /// matches one of the exclude patterns or is nested deeper than
+    /// `max_depth`
+    fn is_excluded(&self, root: &Path, path: &Path) -> bool {
+        let relative = match path.strip_prefix(root) {
+            Ok(relative) => relative,
+            Err(_) => return false
+        };
+        if self.max_depth.is_some_and(|max_depth| relative.components().count() > max_depth) {
+            return true;
+        }
+        if self.exclude.is_empty() {
+            return false;
+        }
+        let relative: Vec<&str> = relative.components()
+            .filter_map(|component| component.as_os_str().to_str())
+            .collect();
+        is_excluded(relative.join("/").as_str(), &self.exclude)
+    }
+
+    /// Brings the page backed by the file at the given path in sync with
+    /// the file system
+    fn apply_change(&mut self, path: &Path) -> Option<WikiEvent> {
+        let (path, url) = self.watched_page(path)?;
+        let index = self.index.get(url.as_str()).cloned();
+        if !path.is_file() {
+            self.pages.remove(index?);
+            self.reindex();
+            return Some(WikiEvent::Deleted(url));
+        }
+        match index {
+            Some(index) => {
+                let page = &mut self.pages[index];
+                if !page.is_dirty() {
+                    if let Err(e) = page.reload() {
+                        println!("Failed reloading {}: {}", path.display(), e);
+                        return None;
+                    }
+                }
+                Some(WikiEvent::Modified(url))
+            }
+            None => {
+                let source = match PageSource::read(path.as_path()) {
+                    Ok(source) => source,
+                    Err(e) => {
+                        println!("Failed loading {}: {}", path.display(), e);
+                        return None;
+                    }
+                };
+                let page = Page::from_source(self.path.clone(), path, url.clone(), source,
+                                             self.markdown_options.clone(),
+                                             self.frontmatter_format);
+                self.index.insert(url.clone(), self.pages.len());
+                self.pages.push(page);
+                Some(WikiEvent::Created(url))
+            }
+        }
+    }
+
+    /// Moves the page backed by the file at `from` to the file at `to`,
+    /// falls back to treating both paths as separate changes
+    fn apply_rename(&mut self, from: &Path, to: &Path) -> Vec<WikiEvent> {
+        let renamed = match (self.watched_page(from), self.watched_page(to)) {
+            (Some((_, old_url)), Some((path, new_url))) => {
+                match self.index.get(old_url.as_str()).cloned() {
+                    Some(index) if path.is_file() && !self.index.contains_key(new_url.as_str()) => {
+                        self.index.remove(old_url.as_str());
+                        self.index.insert(new_url.clone(), index);
+                        let page = &mut self.pages[index];
+                        page.path = path;
+                        page.url = new_url.clone();
+                        Some(WikiEvent::Renamed(old_url, new_url))
+                    }
+                    _ => None
+                }
+            }
+            _ => None
+        };
+        match renamed {
+            Some(event) => vec![event],
+            None => self.apply_change(from).into_iter().chain(self.apply_change(to)).collect()
+        }
+    }
+
+    /// Writes the rendered HTML of every page to the given directory, the
+    /// directories of the URLs are preserved, i.e. `/docs/guide` is
+    /// written to `docs/guide.html`. If `export_redirects` is set, a page
+    /// redirecting to the canonical page is written at every alias
+    /// # Errors
+    /// This will return an error if creating a directory or writing a file
+    /// fails
+    pub fn export_static_site(&self, out_dir: &Path) -> Result<(), WikiError> {
+        self.export(out_dir, |page| String::from(page.html()))
+    }
+
+    /// Same as `export_static_site`, but renders every page as a complete
+    /// HTML document using the template of the wiki
+    /// # Errors
+    /// See `export_static_site`
+    pub fn export_static_site_documents(&self, out_dir: &Path) -> Result<(), WikiError> {
+        self.export(out_dir, |page| self.render_document(page))
+    }
+
+    /// Renders the page as a complete HTML document using the template of
+    /// the wiki
+    pub fn render_document(&self, page: &Page) -> String {
+        self.template.render(page)
+    }
+
+    /// Returns the directories and the name of the file a URL is exported
+    /// to, i.e. `(["docs"], "guide.html")` for `/docs/guide`
+    fn export_path<'a>(&'a self, url: &'a str) -> (Vec<&'a str>, String) {
+        let mut segments: Vec<&str> = url.split('/').filter(|s| !s.is_empty()).collect();
+        let file_name = format!("{}.html", segments.pop().unwrap_or(self.index_name.as_str()));
+        (segments, file_name)
+    }
+
+    /// Writes the result of `render` for every page to an HTML file below
+    /// the given directory, followed by the redirects of the aliases if
+    /// `export_redirects` is set
+    fn export<F>(&self, out_dir: &Path, render: F) -> Result<(), WikiError>
+        where F: Fn(&Page) -> String {
+        let write = |url: &str, html: String| -> Result<(), WikiError> {
+            let (segments, file_name) = self.export_path(url);
+            let dir = segments.iter().fold(out_dir.to_path_buf(), |dir, segment| dir.join(segment));
+            fs::create_dir_all(dir.as_path())?;
+            fs::write(dir.join(file_name), html)?;
+            Ok(())
+        };
+        for page in self.listed_pages() {
+            write(page.url.as_str(), render(page))?;
+        }
+        if !self.export_redirects {
+            return Ok(());
+        }
+        for (alias, &index) in &self.aliases {
+            let page = &self.pages[index];
+            if self.index.contains_key(alias.as_str()) || (page.is_draft() && !self.include_drafts) {
+                continue;
+            }
+            if !is_plain_url(alias.as_str()) {
+                println!("Skipping redirect outside of the export: {}", alias);
+                continue;
+            }
+            // relative to the redirect, so the export works below any prefix
+            let (from, _) = self.export_path(alias.as_str());
+            let (to, file_name) = self.export_path(page.url.as_str());
+            let mut href = "../".repeat(from.len());
+            for segment in to {
+                href.push_str(segment.replace(' ', "%20").as_str());
+                href.push('/');
+            }
+            href.push_str(file_name.replace(' ', "%20").as_str());
+            write(alias.as_str(), template::redirect(href.as_str()))?;
+        }
+        Ok(())
+    }
+
+}
+
+impl Clone for Wiki {
+    /// Clones the wiki and all of its pages, the clone does not watch the
+    /// directory even if the original does
+    fn clone(&self) -> Wiki {
+        Wiki {
+            path: self.path.clone(),
+            pages: self.pages.clone(),
+            markdown_options: self.markdown_options.clone(),
+            extensions: self.extensions.clone(),
+            index_name: self.index_name.clone(),
+            template: self.template.clone(),
+            url_matching: self.url_matching,
+            include_drafts: self.include_drafts,
+            frontmatter_format: self.frontmatter_format,
+            export_redirects: self.export_redirects,
+            exclude: self.exclude.clone(),
+            max_depth: self.max_depth,
+            roots: self.roots.clone(),
+            home_page: self.home_page.clone(),
+            watcher: None,
+            index: self.index.clone(),
+            aliases: self.aliases.clone(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Wiki {
+    type Item = &'a Page;
+    type IntoIter = slice::Iter<'a, Page>;
+
+    fn into_iter(self) -> slice::Iter<'a, Page> {
+        self.iter()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    extern crate tempdir;
+    use self::tempdir::TempDir;
+    use std::fs;
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+    use std::path::MAIN_SEPARATOR;
+    use hoedown::{Extension, FENCED_CODE, TABLES};
+    use hoedown::renderer::html;
+    use chrono::NaiveDate;
+    use yaml_rust::yaml::Yaml;
+    use super::{FrontmatterFormat, Link, MarkdownOptions, OutlineNode, Page, Position, SortKey, Template, UrlMatching, Wiki, WikiBuilder, WikiError, WikiEvent,
+                WikiStats};
+
+    fn make_tmp_wiki(dirname: &str) -> Wiki {
+        let dir = TempDir::new(dirname).expect("create temp dir");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        wiki
+    }
+
+    /// Loads a page with the given raw content from a temporary file with
+    /// the given name, which must not include the extension
+    fn make_page(name: &str, raw: &str) -> Page {
+        let dir = TempDir::new("page").expect("create temp dir");
+        let file_name = format!("{}.md", name);
+        write_file(&dir, &file_name, raw.as_bytes());
+        Page::from_file(dir.path().to_path_buf(), dir.path().join(file_name))
+            .expect("load page")
+    }
+
+    /// Writes a file relative to the given directory, creating any
+    /// missing parent directories
+    fn write_file(dir: &TempDir, name: &str, content: &[u8]) {
+        let path = dir.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).expect("create dirs");
+        fs::write(path, content).expect("write file");
+    }
+
+    #[test]
+    fn test_convert_path_to_url() {
+        assert_eq!(
+            super::convert_path_to_url(
+                "/wikidir",
+                "/lol/what/a/path.md",
+                &["md"]
+            ),
+            "/lol/what/a/path"
+        )
+    }
+
+    #[test]
+    fn test_convert_path_to_url_extension_only() {
+        // file names ending in the characters of the extension must
+        // only lose the extension itself
+        assert_eq!(
+            super::convert_path_to_url("/wikidir", "/wikidir/cmd.md", &["md"]),
+            "/cmd"
+        );
+        assert_eq!(
+            super::convert_path_to_url("/wikidir", "/wikidir/diagram.md", &["md"]),
+            "/diagram"
+        );
+        assert_eq!(
+            super::convert_path_to_url("/wikidir", "/wikidir/readme.md", &["md"]),
+            "/readme"
+        );
+        assert_eq!(
+            super::convert_path_to_url("/wikidir", "/wikidir/notes.md.md", &["md"]),
+            "/notes.md"
+        )
+    }
+
+    #[test]
+    fn test_convert_url_to_path() {
+        assert_eq!(
+            super::convert_url_to_path(
+                "/wikidir",
+                "/lol/what/a/path",
+                "md"
+            ).ok(),
+            Some(String::from("/wikidir/lol/what/a/path.md"))
+        )
+    }
+
+    #[test]
+    fn test_convert_url_to_path_traversal() {
+        for url in &["/../../etc/passwd", "../secret", "/notes/../../secret", "/notes/..",
+                     "/./notes", "/..\\secret", "/notes\\..\\..\\secret"] {
+            match super::convert_url_to_path("/wikidir", url, "md") {
+                Err(WikiError::InvalidUrl(invalid)) => assert_eq!(invalid, *url),
+                other => panic!("expected {} to be rejected, got {:?}", url, other)
+            }
+        }
+        assert_eq!(
+            super::convert_url_to_path("/wikidir", "/notes/2024/..plans/v1.2", "md").ok(),
+            Some(String::from("/wikidir/notes/2024/..plans/v1.2.md"))
+        );
+        assert_eq!(
+            super::convert_url_to_path("/wikidir", "//notes//todo/", "md").ok(),
+            Some(String::from("/wikidir/notes/todo.md"))
+        );
+    }
+
+    #[test]
+    fn test_new_page_traversal() {
+        let dir = TempDir::new("traversal").expect("create temp dir");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert!(wiki.new_page("/../outside").is_err());
+        match wiki.create_page("/notes/../../outside", "escaped") {
+            Err(WikiError::InvalidUrl(url)) => assert_eq!(url, "/notes/../../outside"),
+            _ => panic!("expected an InvalidUrl error")
+        }
+        assert!(!dir.path().parent().unwrap().join("outside.md").exists());
+        wiki.create_page("/notes/inside", "nested").expect("create nested page");
+        assert!(dir.path().join("notes").join("inside.md").is_file());
+    }
+
+    #[test]
+    fn test_convert_path_to_url_overlapping_chars() {
+        // the first characters of the file name are also part
+        // of the base path, they must not be stripped
+        assert_eq!(
+            super::convert_path_to_url(
+                "/wiki",
+                "/wiki/kittens.md",
+                &["md"]
+            ),
+            "/kittens"
+        );
+        assert_eq!(
+            super::convert_path_to_url(
+                "/wiki",
+                "/wiki/wiki/index.md",
+                &["md"]
+            ),
+            "/wiki/index"
+        )
+    }
+
+    #[test]
+    fn test_convert_path_to_url_no_prefix() {
+        assert_eq!(
+            super::convert_path_to_url(
+                "/wikidir",
+                "/elsewhere/page.md",
+                &["md"]
+            ),
+            "/elsewhere/page"
+        )
+    }
+
+    #[test]
+    fn test_convert_path_to_url_separators() {
+        assert_eq!(
+            super::convert_path_to_url("wiki", "wiki\\notes\\todo.md", &["md"]),
+            "/notes/todo"
+        );
+        assert_eq!(
+            super::convert_path_to_url("wiki", "wiki/notes/todo.md", &["md"]),
+            "/notes/todo"
+        );
+        assert_eq!(
+            super::convert_path_to_url("C:\\wiki", "C:\\wiki\\notes\\todo.md", &["md"]),
+            "/notes/todo"
+        )
+    }
+
+    #[test]
+    fn test_convert_url_to_path_separators() {
+        let expected = format!(
+            "wiki{}notes{}todo.md",
+            MAIN_SEPARATOR,
+            MAIN_SEPARATOR
+        );
+        assert_eq!(
+            super::convert_url_to_path("wiki", "/notes/todo", "md").unwrap(),
+            expected
+        );
+        assert_eq!(
+            super::convert_path_to_url(
+                "wiki",
+                super::convert_url_to_path("wiki", "/notes/todo", "md").unwrap().as_str(),
+                &["md"]
+            ),
+            "/notes/todo"
+        )
+    }
+
+    #[test]
+    fn test_get_page_empty() {
+        let wiki = make_tmp_wiki("empty_page");
+        if wiki.get_page("does_not_exist").is_some() {
+            panic!("We got a page although we should not have");
+        }
+    }
+
+    #[test]
+    fn test_new_missing_directory() {
+        let dir = TempDir::new("missing_dir").expect("create temp dir");
+        let missing = dir.path().join("does_not_exist");
+        assert!(Wiki::new(missing.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_new_skips_broken_entries() {
+        let dir = TempDir::new("broken_entries").expect("create temp dir");
+        write_file(&dir, "good.md", b"---\n---\nhello");
+        // not valid utf-8, so it can not be read into a page unless the
+        // encoding fallback is enabled
+        write_file(&dir, "latin.md", b"caf\xe9");
+        #[cfg(unix)]
+        ::std::os::unix::fs::symlink(
+            dir.path().join("nowhere"),
+            dir.path().join("dangling.md")
+        ).expect("create symlink");
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.pages.len(), if cfg!(feature = "encoding_rs") { 2 } else { 1 });
+        assert!(wiki.get_page("/good").is_some());
+    }
+
+    #[test]
+    fn test_create_page() {
+        let dir = TempDir::new("create_page").expect("create temp dir");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        {
+            let page = wiki.create_page("/docs/guide/intro", "# Intro")
+                .expect("create page");
+            assert_eq!(page.url, "/docs/guide/intro");
+            assert_eq!(page.markdown_raw, "# Intro");
+        }
+        assert!(dir.path().join("docs").join("guide").is_dir());
+        assert!(dir.path().join("docs").join("guide").join("intro.md").is_file());
+        assert!(wiki.get_page("/docs/guide/intro").is_some());
+
+        // loading the wiki again must pick up the new page
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let page = wiki.get_page("/docs/guide/intro").expect("get page");
+        assert_eq!(page.markdown_raw, "# Intro\n");
+    }
+
+    #[test]
+    fn test_create_page_existing() {
+        let dir = TempDir::new("create_existing").expect("create temp dir");
+        write_file(&dir, "page.md", b"---\n---\noriginal");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        match wiki.create_page("/page", "overwritten") {
+            Err(WikiError::AlreadyExists(url)) => assert_eq!(url, "/page"),
+            _ => panic!("creating an existing page has to fail")
+        }
+        let content = fs::read_to_string(dir.path().join("page.md")).unwrap();
+        assert_eq!(content, "---\n---\noriginal");
+    }
+
+    #[test]
+    fn test_create_page_root() {
+        let dir = TempDir::new("create_root").expect("create temp dir");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        for url in &["/", "", "//"] {
+            match wiki.create_page(url, "root") {
+                Err(WikiError::InvalidUrl(invalid)) => assert_eq!(invalid, *url),
+                _ => panic!("creating a page at {:?} has to fail", url)
+            }
+        }
+        assert!(!dir.path().join(".md").exists());
+        assert!(wiki.is_empty());
+    }
+
+    #[test]
+    fn test_delete_page() {
+        let dir = TempDir::new("delete_page").expect("create temp dir");
+        write_file(&dir, "keep.md", b"---\n---\nkeep");
+        write_file(&dir, "remove.md", b"---\n---\nremove");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        wiki.delete_page("/remove").expect("delete page");
+        assert!(!dir.path().join("remove.md").exists());
+        assert!(wiki.get_page("/remove").is_none());
+        assert!(wiki.get_page("/keep").is_some());
+        assert_eq!(wiki.pages.len(), 1);
+    }
+
+    #[test]
+    fn test_delete_page_missing() {
+        let mut wiki = make_tmp_wiki("delete_missing");
+        match wiki.delete_page("/does_not_exist") {
+            Err(WikiError::NotFound(url)) => assert_eq!(url, "/does_not_exist"),
+            _ => panic!("deleting a missing page has to fail with NotFound")
+        }
+    }
+
+    #[test]
+    fn test_rename_page() {
+        let dir = TempDir::new("rename_page").expect("create temp dir");
+        write_file(&dir, "old.md", b"---\n---\ncontent");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        wiki.rename_page("/old", "/new").expect("rename page");
+        assert!(!dir.path().join("old.md").exists());
+        assert!(dir.path().join("new.md").is_file());
+        assert!(wiki.get_page("/old").is_none());
+        let page = wiki.get_page("/new").expect("get page");
+        assert_eq!(page.path, dir.path().join("new.md"));
+        assert_eq!(page.markdown_raw, "content");
+    }
+
+    #[test]
+    fn test_rename_page_keeps_order() {
+        let dir = TempDir::new("rename_order").expect("create temp dir");
+        write_file(&dir, "a.md", b"---\n---\n[b](/b)");
+        write_file(&dir, "b.md", b"---\n---\nb");
+        write_file(&dir, "c.md", b"---\n---\nc");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        wiki.rename_page("/a", "/d").expect("rename page");
+        assert_eq!(wiki.urls(), vec!["/b", "/c", "/d"]);
+        wiki.create_page("/ab", "ab").expect("create page");
+        assert_eq!(wiki.urls(), vec!["/ab", "/b", "/c", "/d"]);
+        wiki.rename_page_and_rewrite_links("/b", "/e").expect("rename page");
+        assert_eq!(wiki.urls(), vec!["/ab", "/c", "/d", "/e"]);
+        assert_eq!(wiki.get_page("/d").unwrap().markdown_raw, "[b](/e)");
+        assert_eq!(wiki.get_page("/e").unwrap().markdown_raw, "b");
+    }
+
+    #[test]
+    fn test_rename_page_into_subdirectory() {
+        let dir = TempDir::new("rename_subdir").expect("create temp dir");
+        write_file(&dir, "page.md", b"---\n---\ncontent");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        wiki.rename_page("/page", "/archive/2016/page").expect("rename page");
+        let path = dir.path().join("archive").join("2016").join("page.md");
+        assert!(path.is_file());
+        assert!(!dir.path().join("page.md").exists());
+        let page = wiki.get_page("/archive/2016/page").expect("get page");
+        assert_eq!(page.path, path);
+    }
+
+    #[test]
+    fn test_rename_page_existing_destination() {
+        let dir = TempDir::new("rename_existing").expect("create temp dir");
+        write_file(&dir, "a.md", b"---\n---\na");
+        write_file(&dir, "b.md", b"---\n---\nb");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        match wiki.rename_page("/a", "/b") {
+            Err(WikiError::AlreadyExists(url)) => assert_eq!(url, "/b"),
+            _ => panic!("renaming onto an existing page has to fail")
+        }
+        assert!(wiki.get_page("/a").is_some());
+        assert!(dir.path().join("a.md").is_file());
+    }
+
+    #[test]
+    fn test_rename_page_and_rewrite_links() {
+        let dir = TempDir::new("rename_rewrite").expect("create temp dir");
+        write_file(&dir, "old.md", b"---\n---\n[self](old#top)");
+        write_file(&dir, "a.md", b"---\ntitle: A\n---\nsee [old](/old) and [[old|the old page]]");
+        write_file(&dir, "docs/b.md", b"---\n---\n[up](../old.md#usage), `[code](/old)`\n\n\
+                                          [other]: /a\n[ref]: ../old\n");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let rewritten = wiki.rename_page_and_rewrite_links("/old", "/archive/new")
+            .expect("rename page");
+        assert_eq!(rewritten, 5);
+        assert_eq!(wiki.get_page("/archive/new").unwrap().markdown_raw,
+                   "[self](/archive/new#top)");
+        assert_eq!(wiki.get_page("/a").unwrap().markdown_raw,
+                   "see [old](/archive/new) and [[/archive/new|the old page]]");
+        assert_eq!(wiki.get_page("/docs/b").unwrap().markdown_raw,
+                   "[up](/archive/new#usage), `[code](/old)`\n\n[other]: /a\n[ref]: /archive/new\n");
+        assert!(wiki.iter().all(|page| !page.is_dirty()));
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let page = wiki.get_page("/a").expect("get page");
+        assert_eq!(page.meta.as_ref().unwrap()["title"].as_str(), Some("A"));
+        assert!(page.markdown_raw.contains("[[/archive/new|the old page]]"));
+        assert!(wiki.get_page("/docs/b").unwrap().markdown_raw.contains("(/archive/new#usage)"));
+    }
+
+    #[test]
+    fn test_outbound_internal_links() {
+        let make_page_at = |url: &str, markdown: &str| {
+            Page::new(::std::path::PathBuf::from("wiki"), url, markdown, None).expect("create page")
+        };
+        let page = make_page_at("/docs/guide", "[a](setup) [b](/docs/setup.md#install) \
+                                               [c](../docs/./setup?x=1) [[docs/setup|d]] \
+                                               [[/docs/setup/]] [e](https://example.com) \
+                                               [f](#top) ![g](setup) [h](/Other%20Page) \
+                                               [[Other Page]]");
+        assert_eq!(page.outbound_internal_links(), vec!["/docs/setup", "/Other Page"]);
+        assert_eq!(make_page_at("/a", "[b](b) [[b]]").outbound_internal_links(), vec!["/b"]);
+    }
+
+    #[test]
+    fn test_update_markdown_defers_rendering() {
+        let mut page = make_page("deferred", "---\n---\nfirst");
+        assert_eq!(page.html(), "<p>first</p>\n");
+        for markdown in &["second", "*third*", "**fourth**"] {
+            page.update_markdown(markdown);
+            assert!(page.html.get().is_none());
+        }
+        assert!(page.is_dirty());
+        assert_eq!(page.html(), "<p><strong>fourth</strong></p>\n");
+        let rendered: *const String = page.html.get().unwrap();
+        page.html();
+        assert!(::std::ptr::eq(rendered, page.html.get().unwrap()));
+    }
+
+    #[test]
+    fn test_from_pages() {
+        let base = ::std::path::PathBuf::from("/nonexistent/wiki");
+        let pages = vec![
+            Page::new(base.clone(), "/docs/guide", "# Guide\n\nsee [[docs/setup]]", None).unwrap(),
+            Page::new(base.clone(), "/index", "[guide](docs/guide) and [[missing]]", None).unwrap(),
+        ];
+        let wiki = Wiki::from_pages(base.clone(), pages).expect("build wiki");
+        assert_eq!(wiki.urls(), vec!["/docs/guide", "/index"]);
+        assert_eq!(wiki.get_page("/docs/guide").unwrap().title(), "Guide");
+        assert_eq!(wiki.backlinks("/docs/guide").len(), 1);
+        assert_eq!(wiki.search("guide").len(), 2);
+        assert_eq!(wiki.stats().broken_links, 2);
+
+        let duplicates = vec![Page::new(base.clone(), "/a", "", None).unwrap(),
+                              Page::new(base.clone(), "a/", "", None).unwrap()];
+        match Wiki::from_pages(base.clone(), duplicates) {
+            Err(WikiError::AlreadyExists(url)) => assert_eq!(url, "/a"),
+            _ => panic!("duplicate urls have to be rejected")
+        }
+        let foreign = vec![Page::new(::std::path::PathBuf::from("/elsewhere"), "/a", "", None)
+                               .unwrap()];
+        assert!(Wiki::from_pages(base, foreign).is_err());
+    }
+
+    #[test]
+    fn test_validate_frontmatter() {
+        let dir = TempDir::new("validate").expect("create temp dir");
+        write_file(&dir, "complete.md", b"---\ntitle: Complete\nauthor:\n  name: Jane\n---\n");
+        write_file(&dir, "partial.md", b"---\ntitle: Partial\nauthor: \n---\n");
+        write_file(&dir, "bare.md", b"no frontmatter");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let required = ["title", "author.name"];
+
+        assert!(wiki.get_page("/complete").unwrap().validate_frontmatter(&required).is_ok());
+        assert_eq!(wiki.get_page("/partial").unwrap().validate_frontmatter(&required),
+                   Err(vec![String::from("author.name")]));
+        assert_eq!(wiki.validate_all(&required), vec![
+            (String::from("/bare"), vec![String::from("title"), String::from("author.name")]),
+            (String::from("/partial"), vec![String::from("author.name")]),
+        ]);
+        assert!(wiki.validate_all(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_get_by_path() {
+        let dir = TempDir::new("get_by_path").expect("create temp dir");
+        write_file(&dir, "docs/guide.md", b"# Guide");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let path = dir.path().join("docs").join("guide.md");
+        assert_eq!(wiki.get_by_path(path.as_path()).map(|page| page.url.as_str()),
+                   Some("/docs/guide"));
+        let canonical = fs::canonicalize(path.as_path()).unwrap();
+        assert_eq!(wiki.get_by_path(canonical.as_path()).map(|page| page.url.as_str()),
+                   Some("/docs/guide"));
+        assert!(wiki.get_by_path(dir.path().join("docs").join("missing.md").as_path()).is_none());
+        let outside = TempDir::new("get_by_path_outside").expect("create temp dir");
+        assert!(wiki.get_by_path(outside.path().join("docs").join("guide.md").as_path()).is_none());
+    }
+
+    #[test]
+    fn test_save_normalizes_newlines() {
+        let dir = TempDir::new("save_newlines").expect("create temp dir");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let mut page = wiki.new_page("/page").expect("new page");
+        for body in &["body", "body\n", "body\n\n", "body\r\n\r\n\n"] {
+            page.update_markdown(body);
+            page.save_to_file().expect("save page");
+            assert_eq!(fs::read_to_string(page.path.as_path()).unwrap(), "body\n");
+            assert_eq!(page.markdown_raw, *body);
+        }
+        page.update_markdown("a\r\nb\r\n\r\n");
+        page.save_to_file().expect("save page");
+        assert_eq!(fs::read_to_string(page.path.as_path()).unwrap(), "a\nb\n");
+
+        page.normalize_line_endings = false;
+        page.update_markdown("a\r\nb\r\n\r\n");
+        page.save_to_file().expect("save page");
+        assert_eq!(fs::read_to_string(page.path.as_path()).unwrap(), "a\r\nb\r\n");
+        page.update_markdown("");
+        page.save_to_file().expect("save page");
+        assert_eq!(fs::read_to_string(page.path.as_path()).unwrap(), "");
+    }
+
+    #[test]
+    fn test_frontmatter_as_json() {
+        let page = make_page("json", "---\ntitle: Json\ntags: [a, b]\nauthor:\n  name: Jane\n---\nbody");
+        assert_eq!(page.frontmatter_as_json(),
+                   "{\"author\":{\"name\":\"Jane\"},\"tags\":[\"a\",\"b\"],\"title\":\"Json\"}");
+        assert_eq!(make_page("plain", "body").frontmatter_as_json(), "{}");
+    }
+
+    #[test]
+    fn test_checksum() {
+        let dir = TempDir::new("checksum").expect("create temp dir");
+        write_file(&dir, "page.md", b"---\ntitle: Page\n---\nbody\n");
+        let load = || Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let mut wiki = load();
+        let checksum = wiki.get_page("/page").unwrap().checksum();
+        assert_eq!(checksum.len(), 64);
+        assert!(checksum.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        assert_eq!(load().get_page("/page").unwrap().checksum(), checksum);
+
+        let mut page = wiki.get_page_mut("/page").unwrap();
+        page.update_markdown("changed\n");
+        assert_eq!(page.checksum(), checksum);
+        page.save_to_file().expect("save page");
+        let edited = page.checksum();
+        assert_ne!(edited, checksum);
+        page.set_meta("title", Yaml::String(String::from("Other")));
+        page.save_to_file().expect("save page");
+        assert_ne!(page.checksum(), edited);
+        assert_eq!(load().get_page("/page").unwrap().checksum(), page.checksum());
+
+        // the same as `sha256sum`, even if saving would normalize the file
+        write_file(&dir, "crlf.md", b"---\r\ntitle: Crlf\r\n---\r\nbody\r\n\r\n");
+        assert_eq!(load().get_page("/crlf").unwrap().checksum(),
+                   "67402b0cfe47523e4331d4975c69251749881594b4894127f54160a0e39fcf0e");
+        assert_eq!(Page::new(dir.path().to_path_buf(), "/empty", "", None).unwrap().checksum(),
+                   "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+
+    #[test]
+    fn test_set_markdown_raw() {
+        let mut page = make_page("edits", "---\n---\nfirst");
+        assert_eq!(page.html(), "<p>first</p>\n");
+        assert!(!page.is_dirty());
+        for markdown in &["# Draft", "# Draft\n\nmore", "# Final\n\ndone"] {
+            page.set_markdown_raw(markdown);
+            assert!(page.html.get().is_none());
+            assert!(page.is_dirty());
+        }
+        assert_eq!(page.markdown_raw, "# Final\n\ndone");
+        assert_eq!(page.html(), "<h1 id=\"final\">Final</h1>\n\n<p>done</p>\n");
+        // later accesses use the cached result instead of rendering again
+        let rendered: *const String = page.html.get().unwrap();
+        page.html();
+        assert!(::std::ptr::eq(rendered, page.html.get().unwrap()));
+
+        // unlike `update_markdown` the missing links are left untouched
+        page.markdown_options.mark_missing_links = true;
+        page.missing_links = vec![String::from("/nope")];
+        page.set_markdown_raw("plain");
+        assert_eq!(page.missing_links, vec!["/nope"]);
+        page.set_markdown_raw("[[nope]]");
+        assert_eq!(page.html(), "<p><a href=\"/nope\" class=\"missing\">nope</a></p>\n");
+        page.update_markdown("plain");
+        assert!(page.missing_links.is_empty());
+        page.update_markdown("[[nope]]");
+        assert_eq!(page.html(), "<p><a href=\"/nope\">nope</a></p>\n");
+    }
+
+    #[test]
+    fn test_get_page_mut() {
+        let dir = TempDir::new("get_page_mut").expect("create temp dir");
+        write_file(&dir, "page.md", b"---\n---\nold");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        {
+            let mut page = wiki.get_page_mut("/page").expect("get page");
+            page.update_markdown("*new*");
+            page.save_to_file().expect("save page");
+        }
+        assert_eq!(wiki.get_page("/page").unwrap().html(), "<p><em>new</em></p>\n");
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let page = wiki.get_page("/page").expect("get page");
+        assert_eq!(page.markdown_raw, "*new*\n");
+        assert_eq!(page.html(), "<p><em>new</em></p>\n");
+    }
+
+    #[test]
+    fn test_iter() {
+        let dir = TempDir::new("iter").expect("create temp dir");
+        write_file(&dir, "a.md", b"---\n---\na");
+        write_file(&dir, "sub/b.md", b"---\n---\nb");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        let mut urls: Vec<&str> = wiki.iter().map(|page| page.url.as_str()).collect();
+        urls.sort();
+        assert_eq!(urls, vec!["/a", "/sub/b"]);
+
+        let mut urls = Vec::new();
+        for page in &wiki {
+            urls.push(page.url.as_str());
+        }
+        urls.sort();
+        assert_eq!(urls, vec!["/a", "/sub/b"]);
+    }
+
+    #[test]
+    fn test_get_page_after_delete() {
+        let dir = TempDir::new("index_delete").expect("create temp dir");
+        for name in &["a", "b", "c", "d"] {
+            write_file(&dir, &format!("{}.md", name),
+                       format!("---\n---\n{}", name).as_bytes());
+        }
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        wiki.delete_page("/b").expect("delete page");
+        wiki.delete_page("/a").expect("delete page");
+        assert!(wiki.get_page("/a").is_none());
+        assert!(wiki.get_page("/b").is_none());
+        assert_eq!(wiki.get_page("/c").unwrap().markdown_raw, "c");
+        assert_eq!(wiki.get_page("/d").unwrap().markdown_raw, "d");
+
+        wiki.create_page("/e", "e").expect("create page");
+        wiki.rename_page("/c", "/f").expect("rename page");
+        assert!(wiki.get_page("/c").is_none());
+        assert_eq!(wiki.get_page("/d").unwrap().markdown_raw, "d");
+        assert_eq!(wiki.get_page("/e").unwrap().markdown_raw, "e");
+        assert_eq!(wiki.get_page_mut("/f").unwrap().markdown_raw, "c");
+    }
+
+    #[test]
+    fn test_from_file_broken_frontmatter() {
+        let dir = TempDir::new("broken_frontmatter").expect("create temp dir");
+        write_file(&dir, "broken.md", b"---\ntitle: [unclosed\n---\nbody");
+        match Page::from_file(dir.path().to_path_buf(),
+                              dir.path().join("broken.md")) {
+            Err(WikiError::Frontmatter(msg)) => assert!(!msg.is_empty()),
+            _ => panic!("broken frontmatter has to be reported")
+        }
+    }
+
+    #[test]
+    fn test_from_file_without_frontmatter() {
+        let dir = TempDir::new("no_frontmatter").expect("create temp dir");
+        write_file(&dir, "plain.md", b"just markdown");
+        let page = Page::from_file(dir.path().to_path_buf(),
+                                   dir.path().join("plain.md"))
+            .expect("load page");
+        assert!(page.meta.is_none());
+        assert_eq!(page.markdown_raw, "just markdown");
+        assert_eq!(page.path, dir.path().join("plain.md"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = TempDir::new("non_utf8_path").expect("create temp dir");
+        write_file(&dir, "good.md", b"---\n---\ngood");
+        let path = dir.path().join(OsStr::from_bytes(b"bad\xff.md"));
+        if fs::write(&path, b"---\n---\nbad").is_err() {
+            // the file system does not permit such names
+            return;
+        }
+
+        match Page::from_file(dir.path().to_path_buf(), path) {
+            Err(WikiError::InvalidUrl(_)) => (),
+            _ => panic!("a non utf-8 path has to be rejected")
+        }
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.pages.len(), 1);
+        assert!(wiki.get_page("/good").is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_root() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = TempDir::new("non_utf8_root").expect("create temp dir");
+        let root = dir.path().join(OsStr::from_bytes(b"wiki\xff"));
+        if fs::create_dir(&root).is_err() {
+            // the file system does not permit such names
+            return;
+        }
+        match Wiki::builder().path(&root).build() {
+            Err(WikiError::InvalidUrl(_)) => (),
+            _ => panic!("a non utf-8 root has to be rejected")
+        }
+        assert!(Wiki::from_pages(root.clone(), Vec::new()).is_err());
+
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        wiki.path = root;
+        match wiki.create_page("/page", "text") {
+            Err(WikiError::InvalidUrl(_)) => (),
+            _ => panic!("pages can not be created below a non utf-8 root")
+        }
+        assert!(wiki.new_page("/page").is_err());
+    }
+
+    #[test]
+    fn test_title_from_frontmatter() {
+        let page = make_page("page", "---\ntitle: From Meta\n---\n# From Heading");
+        assert_eq!(page.title(), "From Meta");
+    }
+
+    #[test]
+    fn test_title_from_heading() {
+        let page = make_page("page", "---\nauthor: someone\n---\nintro\n\n# From Heading #\n");
+        assert_eq!(page.title(), "From Heading");
+    }
+
+    #[test]
+    fn test_title_from_url() {
+        let page = make_page("docs/getting-started_guide", "## Not a title");
+        assert_eq!(page.title(), "Getting Started Guide");
+    }
+
+    #[test]
+    fn test_tags_sequence() {
+        let page = make_page("page", "---\ntags: [rust, wiki, 2016]\n---\n");
+        assert_eq!(page.tags(), vec!["rust", "wiki", "2016"]);
+    }
+
+    #[test]
+    fn test_tags_scalar() {
+        let page = make_page("page", "---\ntags: rust\n---\n");
+        assert_eq!(page.tags(), vec!["rust"]);
+    }
+
+    #[test]
+    fn test_tags_missing() {
+        let page = make_page("page", "---\ntitle: Untagged\n---\n");
+        assert!(page.tags().is_empty());
+        let page = make_page("page", "---\ntags:\n  nested: value\n---\n");
+        assert!(page.tags().is_empty());
+        let page = make_page("page", "no frontmatter at all");
+        assert!(page.tags().is_empty());
+    }
+
+    #[test]
+    fn test_pages_by_tag() {
+        let dir = TempDir::new("pages_by_tag").expect("create temp dir");
+        write_file(&dir, "zebra.md", b"---\ntags: [animal, stripes]\n---\n");
+        write_file(&dir, "apple.md", b"---\ntags: fruit\n---\n");
+        write_file(&dir, "sub/ant.md", b"---\ntags: [animal]\n---\n");
+        write_file(&dir, "cat.md", b"---\ntags: [Animal]\n---\n");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        let urls: Vec<&str> = wiki.pages_by_tag("animal").iter()
+            .map(|page| page.url.as_str())
+            .collect();
+        assert_eq!(urls, vec!["/sub/ant", "/zebra"]);
+        assert_eq!(wiki.pages_by_tag("fruit").len(), 1);
+        assert!(wiki.pages_by_tag("missing").is_empty());
+    }
+
+    #[test]
+    fn test_search_tags() {
+        let dir = TempDir::new("search_tags").expect("create temp dir");
+        write_file(&dir, "a.md", b"---\ntags: [rust, Ruby, python]\n---\n");
+        write_file(&dir, "b.md", b"---\ntags: [rust, rustdoc, r]\n---\n");
+        write_file(&dir, "c.md", b"---\ntags: ruby\n---\n");
+        write_file(&dir, "d.md", b"---\ndraft: true\ntags: [rustacean]\n---\n");
+        write_file(&dir, "e.md", b"---\ntags: [Rust, RUSTDOC, Python]\n---\n");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        assert_eq!(wiki.search_tags("ru"), vec!["Ruby", "rust", "rustdoc"]);
+        assert_eq!(wiki.search_tags("RUST"), vec!["rust", "rustdoc"]);
+        assert_eq!(wiki.search_tags(""), vec!["python", "r", "Ruby", "rust", "rustdoc"]);
+        assert!(wiki.search_tags("go").is_empty());
+    }
+
+    #[test]
+    fn test_related_pages() {
+        let dir = TempDir::new("related_pages").expect("create temp dir");
+        write_file(&dir, "rust.md", b"---\ntags: [lang, systems, fast]\n---\n");
+        write_file(&dir, "c.md", b"---\ntitle: C\ntags: [lang, systems, fast, old]\n---\n");
+        write_file(&dir, "zig.md", b"---\ntitle: Zig\ntags: [lang, systems]\n---\n");
+        write_file(&dir, "go.md", b"---\ntitle: Go\ntags: [lang, fast]\n---\n");
+        write_file(&dir, "python.md", b"---\ntitle: Python\ntags: [lang]\n---\n");
+        write_file(&dir, "cooking.md", b"---\ntags: [food]\n---\n");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        let titles = |pages: Vec<&Page>| pages.iter().map(|page| page.title()).collect::<Vec<_>>();
+        assert_eq!(titles(wiki.related_pages("/rust", 10)), vec!["C", "Go", "Zig", "Python"]);
+        assert_eq!(titles(wiki.related_pages("/rust", 2)), vec!["C", "Go"]);
+        assert!(wiki.related_pages("/cooking", 10).is_empty());
+        assert!(wiki.related_pages("/missing", 10).is_empty());
+    }
+
+    #[test]
+    fn test_search() {
+        let dir = TempDir::new("search").expect("create temp dir");
+        write_file(&dir, "one.md", b"---\n---\nRust is fun.");
+        write_file(&dir, "three.md", b"---\ntitle: All about rust\n---\nrust, RUST and Rust!");
+        write_file(&dir, "two.md", b"---\n---\nrust and more rust");
+        write_file(&dir, "none.md", b"---\n---\nnothing to see here");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        let urls: Vec<&str> = wiki.search("RuSt").iter()
+            .map(|page| page.url.as_str())
+            .collect();
+        assert_eq!(urls, vec!["/three", "/two", "/one"]);
+
+        let urls: Vec<&str> = wiki.search("is fun").iter()
+            .map(|page| page.url.as_str())
+            .collect();
+        assert_eq!(urls, vec!["/one"]);
+        assert!(wiki.search("missing").is_empty());
+        assert!(wiki.search("").is_empty());
+    }
+
+    #[test]
+    fn test_markdown_options_tables() {
+        let dir = TempDir::new("tables").expect("create temp dir");
+        let table = "| a | b |\n|---|---|\n| 1 | 2 |\n";
+        write_file(&dir, "table.md", table.as_bytes());
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert!(!wiki.get_page("/table").unwrap().html().contains("<table>"));
+
+        let options = MarkdownOptions {
+            extensions: TABLES | FENCED_CODE,
+            ..MarkdownOptions::default()
+        };
+        let mut wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        assert!(wiki.get_page("/table").unwrap().html().contains("<table>"));
+
+        // new pages use the options of the wiki as well
+        let page = wiki.create_page("/new", table).expect("create page");
+        assert!(page.html().contains("<table>"));
+    }
+
+    #[test]
+    fn test_markdown_options_sanitize() {
+        let markdown = "hello\n\n<script>alert(1)</script>\n\n<b>bold</b>";
+        let mut page = make_page("page", "");
+        page.update_markdown(markdown);
+        assert!(page.html().contains("<script>alert(1)</script>"));
+
+        page.markdown_options.sanitize = true;
+        page.update_markdown(markdown);
+        assert!(!page.html().contains("<script>"));
+        assert!(!page.html().contains("<b>"));
+        assert!(page.html().contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn test_toc() {
+        let page = make_page("page", "# Guide\n\n## Setup\n\n### Linux\n\n\
+                                       ### Windows\n\n## Usage\n\n### Linux\n\n\
+                                       Usage\n-----\n");
+        assert_eq!(page.toc(), vec![
+            (1, String::from("Guide"), String::from("guide")),
+            (2, String::from("Setup"), String::from("setup")),
+            (3, String::from("Linux"), String::from("linux")),
+            (3, String::from("Windows"), String::from("windows")),
+            (2, String::from("Usage"), String::from("usage")),
+            (3, String::from("Linux"), String::from("linux-1")),
+            (2, String::from("Usage"), String::from("usage-1")),
+        ]);
+    }
+
+    #[test]
+    fn test_toc_marker() {
+        let page = make_page("page", "# Guide\n\n[TOC]\n\n## Setup *now*\n\n## Usage\n");
+        assert_eq!(
+            page.html(),
+            "<h1 id=\"guide\">Guide</h1>\n\n<nav class=\"toc\">\n<ul>\n\
+             <li><a href=\"#guide\">Guide</a>\n<ul>\n<li><a href=\"#setup-now\">Setup now</a></li>\n\
+             <li><a href=\"#usage\">Usage</a></li>\n</ul>\n</li>\n</ul>\n</nav>\n\n\
+             <h2 id=\"setup-now\">Setup <em>now</em></h2>\n\n<h2 id=\"usage\">Usage</h2>\n"
+        );
+        assert!(make_page("wikilink", "[[toc]]\n\n# A\n").html().starts_with("<nav class=\"toc\">"));
+
+        let page = make_page("punctuation", "[TOC]\n\n# ...\n\n## Setup\n");
+        assert_eq!(page.toc()[0], (1, String::from("..."), String::from("section")));
+        assert!(page.html().starts_with(
+            "<nav class=\"toc\">\n<ul>\n<li><a href=\"#section\">...</a>\n<ul>\n\
+             <li><a href=\"#setup\">Setup</a></li>"
+        ));
+        assert!(page.html().contains("<h1 id=\"section\">...</h1>"));
+        assert!(!page.html().contains("href=\"#\""));
+        let page = make_page("plain", "# Guide\n\n## Setup\n");
+        assert!(!page.html().contains("<nav"));
+        assert_eq!(make_page("code", "```\n[TOC]\n```\n").html(),
+                   "<p><code>\n[TOC]\n</code></p>\n");
+    }
+
+    #[test]
+    fn test_headings_outline() {
+        fn node(level: u8, text: &str, children: Vec<OutlineNode>) -> OutlineNode {
+            OutlineNode { level, text: String::from(text), anchor: text.to_lowercase(), children }
+        }
+        let page = make_page("page", "# Guide\n\n## Setup\n\n## Usage\n\n### Flags\n");
+        assert_eq!(page.headings_outline(), vec![
+            node(1, "Guide", vec![
+                node(2, "Setup", vec![]),
+                node(2, "Usage", vec![node(3, "Flags", vec![])]),
+            ]),
+        ]);
+
+        let page = make_page("skipped", "### Deep\n\n## Up\n\n# Top\n\n### Child\n\n## Sibling\n");
+        assert_eq!(page.headings_outline(), vec![
+            node(3, "Deep", vec![]),
+            node(2, "Up", vec![]),
+            node(1, "Top", vec![node(3, "Child", vec![]), node(2, "Sibling", vec![])]),
+        ]);
+        assert!(make_page("empty", "no headings").headings_outline().is_empty());
+    }
+
+    #[test]
+    fn test_insert_section() {
+        let mut page = make_page("changelog", "---\ntitle: Changes\n---\n# Changelog\n\n\
+                                               ## Unreleased\n\n- fix\n\n## 1.0\n\n- initial\n");
+        page.insert_section("Unreleased", "- feature", Position::Append);
+        page.insert_section("Unreleased", "Coming soon:", Position::Prepend);
+        assert_eq!(page.markdown_raw, "# Changelog\n\n## Unreleased\n\nComing soon:\n\n- fix\n\n\
+                                       - feature\n\n## 1.0\n\n- initial\n");
+        assert!(page.is_dirty());
+
+        let mut page = make_page("notes", "Some text");
+        page.insert_section("Links", "- [home](/)", Position::Prepend);
+        assert_eq!(page.markdown_raw, "Some text\n\n## Links\n\n- [home](/)\n");
+        assert!(page.html().contains("<h2 id=\"links\">Links</h2>"));
+    }
+
+    #[test]
+    fn test_heading_ids_match_rendering() {
+        // hoedown does not require a space after the `#` of a heading
+        let page = make_page("nospace", "#Intro\n\n## Installation\n");
+        assert_eq!(page.html(), "<h1 id=\"intro\">Intro</h1>\n\n<h2 id=\"installation\">Installation</h2>\n");
+        assert_eq!(page.anchors(), vec!["intro", "installation"]);
+
+        let page = make_page("quoted", "> # Quoted\n\n## Real\n");
+        assert!(page.html().contains("<h1 id=\"quoted\">Quoted</h1>"));
+        assert!(page.html().contains("<h2 id=\"real\">Real</h2>"));
+        assert_eq!(page.toc(), vec![(1, String::from("Quoted"), String::from("quoted")),
+                                    (2, String::from("Real"), String::from("real"))]);
+
+        let page = make_page("marker", "[TOC]\n\n#First\n\n- # listed\n\n## Last");
+        assert!(page.html().starts_with("<nav class=\"toc\">\n<ul>\n<li><a href=\"#first\">First</a>\n\
+                                         <ul>\n<li><a href=\"#last\">Last</a></li>"));
+        assert!(page.html().contains("<li># listed</li>"));
+        assert!(page.html().contains("<h2 id=\"last\">Last</h2>"));
+    }
+
+    #[test]
+    fn test_heading_ids() {
+        let page = make_page("page", "# Guide\n\n## Installation\n\ntext\n\n\
+                                       ## Installation\n");
+        assert!(page.html().contains("<h1 id=\"guide\">Guide</h1>"));
+        assert!(page.html().contains("<h2 id=\"installation\">Installation</h2>"));
+        assert!(page.html().contains("<h2 id=\"installation-1\">Installation</h2>"));
+        for (_, _, slug) in page.toc() {
+            assert!(page.html().contains(format!("id=\"{}\"", slug).as_str()));
+        }
+
+        let page = make_page("punctuation", "# !!!\n\n## ???\n");
+        assert_eq!(page.html(), "<h1 id=\"section\">!!!</h1>\n\n<h2 id=\"section-1\">???</h2>\n");
+    }
+
+    #[test]
+    fn test_wikilinks() {
+        let page = make_page("page", "[[some/page]] and [[other|Other Page]]");
+        assert_eq!(
+            page.html(),
+            "<p><a href=\"/some/page\">some/page</a> and <a href=\"/other\">Other Page</a></p>\n"
+        );
+        assert_eq!(page.markdown_raw, "[[some/page]] and [[other|Other Page]]");
+    }
+
+    #[test]
+    fn test_wikilinks_missing() {
+        let dir = TempDir::new("wikilinks_missing").expect("create temp dir");
+        write_file(&dir, "index.md", b"[[exists]] [[missing|Missing]]");
+        write_file(&dir, "exists.md", b"here");
+        let options = MarkdownOptions {
+            mark_missing_links: true,
+            ..MarkdownOptions::default()
+        };
+        let mut wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        assert_eq!(
+            wiki.get_page("/index").unwrap().html(),
+            "<p><a href=\"/exists\">exists</a> \
+             <a href=\"/missing\" class=\"missing\">Missing</a></p>\n"
+        );
+
+        wiki.create_page("/missing", "now it exists").expect("create page");
+        assert!(!wiki.get_page("/index").unwrap().html().contains("class=\"missing\""));
+        wiki.delete_page("/exists").expect("delete page");
+        assert!(wiki.get_page("/index").unwrap().html()
+                .contains("<a href=\"/exists\" class=\"missing\">"));
+
+        // edits through `get_page_mut` are checked as well
+        wiki.get_page_mut("/index").unwrap().update_markdown("[[nope]] [[missing]]");
+        assert_eq!(
+            wiki.get_page("/index").unwrap().html(),
+            "<p><a href=\"/nope\" class=\"missing\">nope</a> \
+             <a href=\"/missing\">missing</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_wikilinks_missing_resolved() {
+        let dir = TempDir::new("wikilinks_missing_resolved").expect("create temp dir");
+        write_file(&dir, "docs/index.md", b"docs");
+        write_file(&dir, "page.md", b"---\naliases: [/old]\n---\n[[docs]] [[docs/]] [[old]] [[gone]]");
+        let options = MarkdownOptions { mark_missing_links: true, ..MarkdownOptions::default() };
+        let wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        let html = wiki.get_page("/page").unwrap().html();
+        assert_eq!(html.matches("class=\"missing\"").count(), 1);
+        assert!(html.contains("class=\"missing\">gone</a>"));
+        assert!(wiki.get_page("/docs").is_some());
+        assert_eq!(wiki.broken_links(), vec![(String::from("/page"), String::from("/gone"))]);
+    }
+
+    #[test]
+    fn test_resolve_link() {
+        assert_eq!(super::resolve_link("/docs/guide", "../api"), Some(String::from("/api")));
+        assert_eq!(super::resolve_link("/docs/guide", "./intro"),
+                   Some(String::from("/docs/intro")));
+        assert_eq!(super::resolve_link("/docs/guide", "intro.md#setup"),
+                   Some(String::from("/docs/intro")));
+        assert_eq!(super::resolve_link("/docs/guide", "/index"), Some(String::from("/index")));
+        assert_eq!(super::resolve_link("/docs/guide", "http://example.com/a"), None);
+        assert_eq!(super::resolve_link("/docs/guide", "mailto:a@b.c"), None);
+        assert_eq!(super::resolve_link("/docs/guide", "#setup"), None);
+    }
+
+    #[test]
+    fn test_backlinks() {
+        let dir = TempDir::new("backlinks").expect("create temp dir");
+        write_file(&dir, "docs/target.md", b"[self](target)");
+        write_file(&dir, "docs/relative.md", b"see [the target](./target)");
+        write_file(&dir, "wikilink.md", b"see [[docs/target|target]]");
+        write_file(&dir, "unrelated.md", b"[elsewhere](/docs/other) ![img](/docs/target)");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        let urls: Vec<&str> = wiki.backlinks("/docs/target").iter()
+            .map(|page| page.url.as_str())
+            .collect();
+        assert_eq!(urls, vec!["/docs/relative", "/wikilink"]);
+        assert!(wiki.backlinks("/unrelated").is_empty());
+        assert_eq!(wiki.backlinks("docs/target/").len(), 2);
+        assert!(wiki.backlinks("/missing").is_empty());
+    }
+
+    #[test]
+    fn test_backlinks_resolved() {
+        let dir = TempDir::new("backlinks_resolved").expect("create temp dir");
+        write_file(&dir, "a.md", b"---\naliases: [/old-a]\n---\na");
+        write_file(&dir, "docs/index.md", b"docs");
+        write_file(&dir, "links.md", b"[a](/a) [docs](/docs) [[old-a]]");
+        write_file(&dir, "other.md", b"[docs](docs/)");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        let urls = |url: &str| -> Vec<String> {
+            wiki.backlinks(url).iter().map(|page| page.url.clone()).collect()
+        };
+        assert_eq!(urls("a"), vec!["/links"]);
+        assert_eq!(urls("/old-a"), vec!["/links"]);
+        assert_eq!(urls("/docs/index"), vec!["/links", "/other"]);
+        assert_eq!(urls("/docs"), vec!["/links", "/other"]);
+        assert!(!wiki.orphaned_pages().iter().any(|page| page.url == "/docs/index"));
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = TempDir::new("reload").expect("create temp dir");
+        write_file(&dir, "changed.md", b"before");
+        write_file(&dir, "deleted.md", b"deleted");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.get_page("/changed").unwrap().markdown_raw, "before");
+
+        write_file(&dir, "changed.md", b"after");
+        write_file(&dir, "added.md", b"added");
+        fs::remove_file(dir.path().join("deleted.md")).expect("remove file");
+        wiki.reload().expect("reload wiki");
+
+        assert_eq!(wiki.path, dir.path());
+        assert_eq!(wiki.get_page("/changed").unwrap().markdown_raw, "after");
+        assert_eq!(wiki.get_page("/added").unwrap().markdown_raw, "added");
+        assert!(wiki.get_page("/deleted").is_none());
+        assert_eq!(wiki.pages.len(), 2);
+    }
+
+    #[test]
+    fn test_page_reload() {
+        let dir = TempDir::new("page_reload").expect("create temp dir");
+        write_file(&dir, "page.md", b"---\ntitle: Before\n---\n*before*");
+        let mut page = Page::from_file(dir.path().to_path_buf(), dir.path().join("page.md"))
+            .expect("load page");
+        assert_eq!(page.title(), "Before");
+
+        write_file(&dir, "page.md", b"---\ntitle: After\n---\n**after**");
+        page.reload().expect("reload page");
+        assert_eq!(page.title(), "After");
+        assert_eq!(page.markdown_raw, "**after**");
+        assert_eq!(page.html(), "<p><strong>after</strong></p>\n");
+    }
+
+    #[test]
+    fn test_html_lazy() {
+        let mut page = make_page("page", "*lazy*");
+        assert!(page.html.get().is_none());
+        let first = page.html().as_ptr();
+        assert!(page.html.get().is_some());
+        assert_eq!(page.html(), "<p><em>lazy</em></p>\n");
+        // the cached html is returned rather than rendering it again
+        assert_eq!(page.html().as_ptr(), first);
+
+        page.update_markdown("**changed**");
+        assert!(page.html.get().is_none());
+        assert_eq!(page.html(), "<p><strong>changed</strong></p>\n");
+    }
+
+    #[test]
+    fn test_load_many_pages() {
+        let dir = TempDir::new("many_pages").expect("create temp dir");
+        for i in 0..200 {
+            write_file(&dir, &format!("dir{}/page{:03}.md", i % 7, i),
+                       format!("---\n---\npage {}", i).as_bytes());
+        }
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.pages.len(), 200);
+
+        let urls: Vec<&str> = wiki.iter().map(|page| page.url.as_str()).collect();
+        let mut sorted = urls.clone();
+        sorted.sort();
+        assert_eq!(urls, sorted);
+        for i in 0..200 {
+            let url = format!("/dir{}/page{:03}", i % 7, i);
+            let page = wiki.get_page(url.as_str()).expect("get page");
+            assert_eq!(page.markdown_raw, format!("page {}", i));
+        }
+
+        let again = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let again: Vec<&str> = again.iter().map(|page| page.url.as_str()).collect();
+        assert_eq!(urls, again);
+    }
+
+    #[test]
+    fn test_set_meta_without_frontmatter() {
+        let dir = TempDir::new("set_meta_new").expect("create temp dir");
+        write_file(&dir, "page.md", b"body");
+        let mut page = Page::from_file(dir.path().to_path_buf(), dir.path().join("page.md"))
+            .expect("load page");
+        assert!(page.get_meta("title").is_none());
+        assert!(!page.is_dirty());
+
+        page.set_meta("title", Yaml::String(String::from("New Title")));
+        assert!(page.is_dirty());
+        assert_eq!(page.get_meta("title").and_then(|t| t.as_str()), Some("New Title"));
+        page.save_to_file().expect("save page");
+        assert!(!page.is_dirty());
+
+        let page = Page::from_file(dir.path().to_path_buf(), dir.path().join("page.md"))
+            .expect("load page");
+        assert_eq!(page.title(), "New Title");
+        assert_eq!(page.markdown_raw, "body\n");
+    }
+
+    #[test]
+    fn test_set_meta_overwrite() {
+        let dir = TempDir::new("set_meta_overwrite").expect("create temp dir");
+        write_file(&dir, "page.md", b"---\ntitle: Old\ntags: [a]\n---\nbody");
+        let mut page = Page::from_file(dir.path().to_path_buf(), dir.path().join("page.md"))
+            .expect("load page");
+        assert_eq!(page.get_meta("title").and_then(|t| t.as_str()), Some("Old"));
+
+        page.set_meta("title", Yaml::String(String::from("New")));
+        page.save_to_file().expect("save page");
+        let page = Page::from_file(dir.path().to_path_buf(), dir.path().join("page.md"))
+            .expect("load page");
+        assert_eq!(page.title(), "New");
+        assert_eq!(page.tags(), vec!["a"]);
+    }
+
+    #[test]
+    fn test_save_without_frontmatter() {
+        let dir = TempDir::new("save_plain").expect("create temp dir");
+        let content = b"# Plain\n\nJust markdown, no frontmatter.\n";
+        write_file(&dir, "plain.md", content);
+        let mut page = Page::from_file(dir.path().to_path_buf(), dir.path().join("plain.md"))
+            .expect("load page");
+        page.save_to_file().expect("save page");
+        assert_eq!(fs::read(dir.path().join("plain.md")).unwrap(), content.to_vec());
+    }
+
+    #[test]
+    fn test_save_unmodified_page() {
+        let dir = TempDir::new("save_unmodified").expect("create temp dir");
+        write_file(&dir, "page.md", b"---\ntitle: Page\n---\nbody");
+        let path = dir.path().join("page.md");
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).expect("mtime");
+
+        thread::sleep(Duration::from_millis(20));
+        let mut page = Page::from_file(dir.path().to_path_buf(), path.clone())
+            .expect("load page");
+        page.save_to_file().expect("save page");
+        assert_eq!(fs::metadata(&path).and_then(|m| m.modified()).expect("mtime"), modified);
+
+        page.update_markdown("changed");
+        assert!(page.is_dirty());
+        page.save_to_file().expect("save page");
+        assert!(!page.is_dirty());
+        assert!(fs::metadata(&path).and_then(|m| m.modified()).expect("mtime") > modified);
+    }
+
+    #[test]
+    fn test_save_all() {
+        let dir = TempDir::new("save_all").expect("create temp dir");
+        write_file(&dir, "a.md", b"a");
+        write_file(&dir, "b.md", b"b");
+        write_file(&dir, "c.md", b"c");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.save_all().expect("save all"), 0);
+
+        wiki.get_page_mut("/a").unwrap().update_markdown("changed a");
+        wiki.get_page_mut("/c").unwrap().set_meta("title", Yaml::String(String::from("C")));
+        assert_eq!(wiki.save_all().expect("save all"), 2);
+        assert_eq!(wiki.save_all().expect("save all"), 0);
+
+        assert_eq!(fs::read_to_string(dir.path().join("a.md")).unwrap(), "changed a\n");
+        assert_eq!(fs::read_to_string(dir.path().join("b.md")).unwrap(), "b");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.get_page("/c").unwrap().title(), "C");
+    }
+
+    #[test]
+    fn test_save_all_error() {
+        let dir = TempDir::new("save_all_error").expect("create temp dir");
+        write_file(&dir, "a.md", b"a");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        fs::remove_dir_all(dir.path()).expect("remove wiki");
+
+        wiki.get_page_mut("/a").unwrap().update_markdown("changed");
+        match wiki.save_all() {
+            Err(WikiError::Save(url, _)) => assert_eq!(url, "/a"),
+            other => panic!("expected save error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_convert_path_to_url_extensions() {
+        let extensions = ["md", "markdown"];
+        assert_eq!(
+            super::convert_path_to_url("/wiki", "/wiki/notes.markdown", &extensions),
+            "/notes"
+        );
+        assert_eq!(
+            super::convert_path_to_url("/wiki", "/wiki/notes.md", &extensions),
+            "/notes"
+        );
+        assert_eq!(
+            super::convert_path_to_url("/wiki", "/wiki/notes.txt", &extensions),
+            "/notes.txt"
+        );
+        assert_eq!(
+            super::convert_path_to_url("/wiki", "/wiki/markdown", &extensions),
+            "/markdown"
+        )
+    }
+
+    #[test]
+    fn test_load_markdown_extension() {
+        let dir = TempDir::new("extensions").expect("create temp dir");
+        write_file(&dir, "notes.markdown", b"notes");
+        write_file(&dir, "page.md", b"page");
+        write_file(&dir, "ignored.txt", b"ignored");
+        let mut wiki = Wiki::with_extensions(dir.path().to_str().unwrap(), &["markdown", "md"])
+            .expect("load wiki");
+        let urls: Vec<&str> = wiki.iter().map(|page| page.url.as_str()).collect();
+        assert_eq!(urls, vec!["/notes", "/page"]);
+
+        let page = wiki.get_page("/notes").unwrap();
+        assert_eq!(page.path, dir.path().join("notes.markdown"));
+        assert_eq!(wiki.new_page("/notes").unwrap().path, page.path);
+
+        wiki.rename_page("/page", "/moved").expect("rename page");
+        assert!(dir.path().join("moved.md").is_file());
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let urls: Vec<&str> = wiki.iter().map(|page| page.url.as_str()).collect();
+        assert_eq!(urls, vec!["/moved"]);
+    }
+
+    #[test]
+    fn test_word_count() {
+        let words = vec!["word"; 400].join(" ");
+        let page = make_page("words", format!("---\ntitle: Not Counted\n---\n{}", words).as_str());
+        assert_eq!(page.word_count(), 400);
+        assert_eq!(page.reading_time(), Duration::from_secs(120));
+        assert_eq!(page.reading_time_at(100), Duration::from_secs(240));
+    }
+
+    #[test]
+    fn test_word_count_code() {
+        let page = make_page("code", "one two\n\n```\nfn main() {}\n```\nthree");
+        assert_eq!(page.word_count(), 6);
+        assert_eq!(page.word_count_without_code(), 3);
+        assert_eq!(page.reading_time_at(60), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_summary_description() {
+        let page = make_page("described", "---\ndescription: From the frontmatter\n---\nThe body.");
+        assert_eq!(page.summary(100), "From the frontmatter");
+    }
+
+    #[test]
+    fn test_summary_first_paragraph() {
+        let page = make_page(
+            "paragraph",
+            "# Heading\n\nThe **first** paragraph\nlinks [[elsewhere]].\n\nThe second."
+        );
+        assert_eq!(page.summary(100), "The first paragraph links elsewhere.");
+        assert_eq!(make_page("empty", "# Heading").summary(100), "");
+    }
+
+    #[test]
+    fn test_summary_truncation() {
+        let page = make_page("long", "The quick brown fox jumps over the lazy dog");
+        assert_eq!(page.summary(43), "The quick brown fox jumps over the lazy dog");
+        assert_eq!(page.summary(18), "The quick brown\u{2026}");
+        assert_eq!(page.summary(17), "The quick brown\u{2026}");
+        assert_eq!(page.summary(16), "The quick brown\u{2026}");
+        assert_eq!(page.summary(3), "Th\u{2026}");
+    }
+
+    #[test]
+    fn test_tree() {
+        let dir = TempDir::new("tree").expect("create temp dir");
+        write_file(&dir, "a.md", b"a");
+        write_file(&dir, "a/c.md", b"c");
+        write_file(&dir, "a/b.md", b"b");
+        write_file(&dir, "x/y/z.md", b"z");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let tree = wiki.tree();
+
+        assert_eq!(tree.name, "");
+        assert!(tree.page.is_none());
+        let names: Vec<&str> = tree.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "x"]);
+
+        let a = tree.child("a").unwrap();
+        assert_eq!(a.page.map(|p| p.url.as_str()), Some("/a"));
+        let names: Vec<&str> = a.children.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["b", "c"]);
+        assert!(a.children.iter().all(|n| n.is_leaf() && n.page.is_some()));
+
+        let y = tree.child("x").and_then(|x| x.child("y")).unwrap();
+        assert!(y.page.is_none());
+        assert_eq!(y.child("z").and_then(|z| z.page).map(|p| p.url.as_str()), Some("/x/y/z"));
+    }
+
+    #[test]
+    fn test_get_page_index() {
+        let dir = TempDir::new("index_page").expect("create temp dir");
+        write_file(&dir, "docs/index.md", b"docs index");
+        write_file(&dir, "guide.md", b"guide");
+        write_file(&dir, "guide/index.md", b"guide index");
+        write_file(&dir, "other/home.md", b"other home");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        assert_eq!(wiki.get_page("/docs").map(|p| p.url.as_str()), Some("/docs/index"));
+        assert_eq!(wiki.get_page("/docs/index").map(|p| p.url.as_str()), Some("/docs/index"));
+        assert_eq!(wiki.get_page("/guide").map(|p| p.url.as_str()), Some("/guide"));
+        assert!(wiki.get_page("/other").is_none());
+
+        wiki.index_name = String::from("home");
+        assert_eq!(wiki.get_page_mut("/other").map(|p| p.url.clone()).as_deref(),
+                   Some("/other/home"));
+        assert!(wiki.get_page("/docs").is_none());
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(super::normalize_url("/page"), "/page");
+        assert_eq!(super::normalize_url("/page/"), "/page");
+        assert_eq!(super::normalize_url("//docs///page"), "/docs/page");
+        assert_eq!(super::normalize_url("docs/page"), "/docs/page");
+        assert_eq!(super::normalize_url("/"), "/");
+        assert_eq!(super::normalize_url(""), "/");
+    }
+
+    #[test]
+    fn test_get_page_normalized() {
+        let dir = TempDir::new("normalized").expect("create temp dir");
+        write_file(&dir, "page.md", b"page");
+        write_file(&dir, "docs/guide.md", b"guide");
+        let base = format!("{}/", dir.path().to_str().unwrap());
+        let mut wiki = Wiki::new(base.as_str()).expect("load wiki");
+
+        let urls: Vec<&str> = wiki.iter().map(|page| page.url.as_str()).collect();
+        assert_eq!(urls, vec!["/docs/guide", "/page"]);
+        for url in &["/page", "/page/", "//page", "page", "page//"] {
+            assert_eq!(wiki.get_page(url).map(|p| p.url.as_str()), Some("/page"));
+        }
+        assert_eq!(wiki.get_page_mut("docs//guide/").map(|p| p.url.clone()).as_deref(),
+                   Some("/docs/guide"));
+        assert!(wiki.get_page("/").is_none());
+    }
+
+    #[test]
+    fn test_resolve_relative_links() {
+        let dir = TempDir::new("relative_links").expect("create temp dir");
+        write_file(&dir, "docs/guide.md", b"[api](../api) [intro](./intro#usage) \
+            [faq](faq.md) [abs](/docs/x) [ext](http://example.com/a) [top](#top)");
+        let options = MarkdownOptions {
+            resolve_relative_links: true,
+            ..MarkdownOptions::default()
+        };
+        let wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        let html = wiki.get_page("/docs/guide").unwrap().html();
+        assert!(html.contains("<a href=\"/api\">api</a>"));
+        assert!(html.contains("<a href=\"/docs/intro#usage\">intro</a>"));
+        assert!(html.contains("<a href=\"/docs/faq\">faq</a>"));
+        assert!(html.contains("<a href=\"/docs/x\">abs</a>"));
+        assert!(html.contains("<a href=\"http://example.com/a\">ext</a>"));
+        assert!(html.contains("<a href=\"#top\">top</a>"));
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let html = wiki.get_page("/docs/guide").unwrap().html();
+        assert!(html.contains("<a href=\"../api\">api</a>"));
+    }
+
+    #[test]
+    fn test_resolve_relative_images() {
+        let dir = TempDir::new("relative_images").expect("create temp dir");
+        write_file(&dir, "docs/guide/intro.md", b"![diagram](images/diagram.png) \
+            ![up](../logo.png) ![remote](https://example.com/a.png) [link](images/x)");
+        let options = MarkdownOptions {
+            resolve_relative_images: true,
+            ..MarkdownOptions::default()
+        };
+        let wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        let html = wiki.get_page("/docs/guide/intro").unwrap().html();
+        assert!(html.contains("<img src=\"/docs/guide/images/diagram.png\" alt=\"diagram\">"));
+        assert!(html.contains("<img src=\"/docs/logo.png\" alt=\"up\">"));
+        assert!(html.contains("<img src=\"https://example.com/a.png\" alt=\"remote\">"));
+        assert!(html.contains("<a href=\"images/x\">link</a>"));
+    }
+
+    #[test]
+    fn test_urls() {
+        let dir = TempDir::new("urls").expect("create temp dir");
+        write_file(&dir, "zebra.md", b"z");
+        write_file(&dir, "apple.md", b"a");
+        write_file(&dir, "docs/intro.md", b"i");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.urls(), vec!["/apple", "/docs/intro", "/zebra"]);
+
+        wiki.create_page("/banana", "b").expect("create page");
+        assert_eq!(wiki.urls(), vec!["/apple", "/banana", "/docs/intro", "/zebra"]);
+    }
+
+    #[test]
+    fn test_broken_links() {
+        let dir = TempDir::new("broken_links").expect("create temp dir");
+        write_file(&dir, "index.md", b"[exists](exists) [gone](gone) [[also gone]] [[exists]] \
+            [again](/gone) [web](https://example.com) [mail](mailto:a@example.com)");
+        write_file(&dir, "exists.md", b"[home](index#top) ![missing image](missing.png)");
+        write_file(&dir, "docs/index.md", b"[up](../docs)");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.broken_links(), vec![
+            (String::from("/index"), String::from("/gone")),
+            (String::from("/index"), String::from("/also gone")),
+        ]);
+    }
+
+    #[test]
+    fn test_sitemap_xml() {
+        let dir = TempDir::new("sitemap").expect("create temp dir");
+        write_file(&dir, "a.md", b"a");
+        write_file(&dir, "docs/b & c.md", b"b");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let xml = wiki.sitemap_xml("https://example.com/wiki/");
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                                 <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">"));
+        assert!(xml.trim_end().ends_with("</urlset>"));
+        assert_eq!(xml.matches("<url>").count(), 2);
+        assert_eq!(xml.matches("<lastmod>").count(), 2);
+        assert!(xml.contains("<loc>https://example.com/wiki/a</loc>"));
+        assert!(xml.contains("<loc>https://example.com/wiki/docs/b%20&amp;%20c</loc>"));
+    }
+
+    #[test]
+    fn test_rss_feed() {
+        let dir = TempDir::new("rss").expect("create temp dir");
+        let now = SystemTime::now();
+        for (i, name) in ["old", "newest", "middle", "oldest"].iter().enumerate() {
+            write_file(&dir, &format!("{}.md", name), format!("# {}\n\n*{}*", name, i).as_bytes());
+        }
+        for &(name, age) in &[("newest", 0), ("middle", 60), ("old", 120), ("oldest", 180)] {
+            let file = fs::File::options().write(true).open(dir.path().join(format!("{}.md", name)))
+                .expect("open file");
+            file.set_modified(now - Duration::from_secs(age)).expect("set mtime");
+        }
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let xml = wiki.rss_feed("https://example.com", 3);
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">"));
+        assert_eq!(xml.matches("<item>").count(), 3);
+        let newest = xml.find("<title>newest</title>").expect("newest item");
+        let middle = xml.find("<title>middle</title>").expect("middle item");
+        let old = xml.find("<title>old</title>").expect("old item");
+        assert!(newest < middle && middle < old);
+        assert!(!xml.contains("<title>oldest</title>"));
+        assert!(xml.contains("<link>https://example.com/middle</link>"));
+        assert!(xml.contains("<description><![CDATA[<h1 id=\"newest\">newest</h1>"));
+        assert_eq!(xml.matches("<pubDate>").count(), 3);
+    }
+
+    #[test]
+    fn test_export_static_site() {
+        let dir = TempDir::new("export").expect("create temp dir");
+        write_file(&dir, "index.md", b"# Home");
+        write_file(&dir, "docs/guide.md", b"---\ntitle: The <Guide>\n---\n*guide*");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        let out = TempDir::new("export_out").expect("create temp dir");
+        wiki.export_static_site(out.path()).expect("export site");
+        assert_eq!(fs::read_to_string(out.path().join("index.html")).unwrap(),
+                   "<h1 id=\"home\">Home</h1>\n");
+        assert_eq!(fs::read_to_string(out.path().join("docs").join("guide.html")).unwrap(),
+                   "<p><em>guide</em></p>\n");
+
+        let out = TempDir::new("export_documents").expect("create temp dir");
+        wiki.export_static_site_documents(out.path()).expect("export site");
+        let guide = fs::read_to_string(out.path().join("docs").join("guide.html")).unwrap();
+        assert!(guide.starts_with("<!DOCTYPE html>"));
+        assert!(guide.contains("<title>The &lt;Guide&gt;</title>"));
+        assert!(guide.contains("<main>\n<p><em>guide</em></p>\n</main>"));
+    }
+
+    #[test]
+    fn test_export_redirects() {
+        let dir = TempDir::new("export_redirects").expect("create temp dir");
+        write_file(&dir, "docs/guide.md", b"---\naliases: [/old/guide, /manual]\n---\n*guide*");
+        write_file(&dir, "manual.md", b"# Manual");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        let out = TempDir::new("export_redirects_out").expect("create temp dir");
+        wiki.export_static_site(out.path()).expect("export site");
+        assert!(!out.path().join("old").exists());
+
+        wiki.export_redirects = true;
+        wiki.export_static_site(out.path()).expect("export site");
+        let redirect = fs::read_to_string(out.path().join("old").join("guide.html")).unwrap();
+        assert!(redirect.contains("<meta http-equiv=\"refresh\" content=\"0; url=../docs/guide.html\">"));
+        assert!(redirect.contains("<link rel=\"canonical\" href=\"../docs/guide.html\">"));
+        // aliases never replace existing pages
+        assert_eq!(fs::read_to_string(out.path().join("manual.html")).unwrap(),
+                   "<h1 id=\"manual\">Manual</h1>\n");
+    }
+
+    #[test]
+    fn test_export_redirects_traversal() {
+        let dir = TempDir::new("export_traversal").expect("create temp dir");
+        write_file(&dir, "page.md", b"---\naliases: [/../../escaped, /a/../b, /ok]\n---\ntext");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        wiki.export_redirects = true;
+
+        let out = TempDir::new("export_traversal_out").expect("create temp dir");
+        let site = out.path().join("out").join("site");
+        wiki.export_static_site(site.as_path()).expect("export site");
+        assert!(site.join("ok.html").exists());
+        assert!(!site.join("b.html").exists());
+        assert!(!out.path().join("escaped.html").exists());
+        assert!(!out.path().join("out").join("escaped.html").exists());
+    }
+
+    #[test]
+    fn test_render_document_template() {
+        let dir = TempDir::new("template").expect("create temp dir");
+        write_file(&dir, "docs/page.md", b"---\ntitle: Fish & Chips\n---\nliteral {{title}}");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let page = wiki.get_page("/docs/page").unwrap();
+
+        let document = wiki.render_document(page);
+        assert!(document.contains("<title>Fish &amp; Chips</title>"));
+        assert!(document.contains("<main>\n<p>literal {{title}}</p>\n</main>"));
+
+        wiki.template = Template::new("<h1>{{ title }}</h1><a href=\"{{url}}\">{{content}}</a>{{other}}");
+        let page = wiki.get_page("/docs/page").unwrap();
+        assert_eq!(
+            wiki.render_document(page),
+            "<h1>Fish &amp; Chips</h1><a href=\"/docs/page\"><p>literal {{title}}</p>\n</a>{{other}}"
+        );
+    }
+
+    /// Waits a few seconds for the first event of the watched wiki
+    fn wait_for_event(wiki: &mut Wiki) -> Option<WikiEvent> {
+        wiki.wait_for_changes(Duration::from_secs(5)).into_iter().next()
+    }
+
+    #[test]
+    fn test_watch() {
+        let dir = TempDir::new("watch").expect("create temp dir");
+        write_file(&dir, "existing.md", b"existing");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert!(wiki.wait_for_changes(Duration::from_millis(10)).is_empty());
+        wiki.watch().expect("watch wiki");
+        assert!(wiki.apply_changes().is_empty());
+
+        write_file(&dir, "docs/new.md", b"new");
+        assert_eq!(wait_for_event(&mut wiki),
+                   Some(WikiEvent::Created(String::from("/docs/new"))));
+        assert_eq!(wiki.get_page("/docs/new").unwrap().markdown_raw, "new");
+
+        write_file(&dir, "existing.md", b"changed");
+        assert_eq!(wait_for_event(&mut wiki),
+                   Some(WikiEvent::Modified(String::from("/existing"))));
+        assert_eq!(wiki.get_page("/existing").unwrap().markdown_raw, "changed");
+
+        fs::remove_file(dir.path().join("existing.md")).expect("remove file");
+        assert_eq!(wait_for_event(&mut wiki),
+                   Some(WikiEvent::Deleted(String::from("/existing"))));
+        assert!(wiki.get_page("/existing").is_none());
+
+        fs::rename(dir.path().join("docs").join("new.md"), dir.path().join("moved.md"))
+            .expect("rename file");
+        assert_eq!(wait_for_event(&mut wiki),
+                   Some(WikiEvent::Renamed(String::from("/docs/new"), String::from("/moved"))));
+        assert_eq!(wiki.get_page("/moved").unwrap().path, dir.path().join("moved.md"));
+    }
+
+    #[test]
+    fn test_last_modified() {
+        let dir = TempDir::new("last_modified").expect("create temp dir");
+        write_file(&dir, "page.md", b"page");
+        let path = dir.path().join("page.md");
+        let past = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(&path).expect("open file")
+            .set_modified(past).expect("set mtime");
+
+        let mut page = Page::from_file(dir.path().to_path_buf(), path.clone()).expect("load page");
+        assert_eq!(page.last_modified().expect("mtime"), past);
+
+        // touching the file is only picked up once the page is reloaded
+        let touched = past + Duration::from_secs(60);
+        fs::File::options().write(true).open(&path).expect("open file")
+            .set_modified(touched).expect("set mtime");
+        assert_eq!(page.last_modified().expect("mtime"), past);
+        page.reload().expect("reload page");
+        assert_eq!(page.last_modified().expect("mtime"), touched);
+
+        page.update_markdown("changed");
+        page.save_to_file().expect("save page");
+        assert!(page.last_modified().expect("mtime") > touched);
+
+        let unsaved = Page::new(dir.path().to_path_buf(), "/unsaved", "", None).unwrap();
+        assert!(unsaved.last_modified().is_err());
+    }
+
+    #[test]
+    fn test_clone_page() {
+        let page = make_page("original", "---\ntitle: Original\n---\n*original*");
+        assert_eq!(page.html(), "<p><em>original</em></p>\n");
+        let mut clone = page.clone();
+        assert_eq!(clone.html(), "<p><em>original</em></p>\n");
+
+        clone.update_markdown("**changed**");
+        clone.set_meta("title", Yaml::String(String::from("Changed")));
+        assert_eq!(clone.html(), "<p><strong>changed</strong></p>\n");
+        assert_eq!(clone.title(), "Changed");
+        assert_eq!(page.markdown_raw, "*original*");
+        assert_eq!(page.html(), "<p><em>original</em></p>\n");
+        assert_eq!(page.title(), "Original");
+        assert!(!page.is_dirty());
+    }
+
+    #[test]
+    fn test_clone_wiki() {
+        let dir = TempDir::new("clone_wiki").expect("create temp dir");
+        write_file(&dir, "a.md", b"a");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let mut clone = wiki.clone();
+        clone.get_page_mut("/a").unwrap().update_markdown("changed");
+        assert_eq!(clone.get_page("/a").unwrap().markdown_raw, "changed");
+        assert_eq!(wiki.get_page("/a").unwrap().markdown_raw, "a");
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Page>();
+        assert_send_sync::<Wiki>();
+    }
+
+    #[test]
+    fn test_builder() {
+        let dir = TempDir::new("builder").expect("create temp dir");
+        write_file(&dir, "docs/readme.markdown", b"<b>docs</b> | a |\n|---|---|\n| b | c |");
+        write_file(&dir, "ignored.md", b"ignored");
+        let wiki = WikiBuilder::default()
+            .path(dir.path().to_str().unwrap())
+            .extensions(&["markdown"])
+            .index_name("readme")
+            .markdown_options(MarkdownOptions { extensions: TABLES, ..MarkdownOptions::default() })
+            .sanitize(true)
+            .template(Template::new("<title>{{title}}</title>"))
+            .build()
+            .expect("load wiki");
+
+        assert_eq!(wiki.urls(), vec!["/docs/readme"]);
+        assert_eq!(wiki.index_name, "readme");
+        assert!(wiki.markdown_options.sanitize);
+        let page = wiki.get_page("/docs").expect("index page");
+        assert!(page.html().contains("<table>"));
+        assert!(page.html().contains("&lt;b&gt;docs&lt;/b&gt;"));
+        assert_eq!(wiki.render_document(page), "<title>Readme</title>");
+    }
+
+    #[test]
+    fn test_builder_missing_directory() {
+        let dir = TempDir::new("builder_missing").expect("create temp dir");
+        let path = dir.path().join("missing");
+        assert!(Wiki::builder().path(path.to_str().unwrap()).build().is_err());
+    }
+
+    #[test]
+    fn test_info() {
+        let page = make_page("info", "---\ntitle: Info\ntags: [a, b]\n---\nThe summary.");
+        let info = page.info();
+        assert_eq!(info.url, "/info");
+        assert_eq!(info.title, "Info");
+        assert_eq!(info.tags, vec!["a", "b"]);
+        assert_eq!(info.summary, "The summary.");
+        assert!(info.last_modified.is_some());
+    }
+
+    #[cfg(feature = "encoding_rs")]
+    #[test]
+    fn test_read_latin1_page() {
+        let dir = TempDir::new("latin1").expect("create temp dir");
+        write_file(&dir, "cafe.md", b"---\ntitle: Caf\xe9\n---\nVoil\xe0, na\xefve r\xe9sum\xe9");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let page = wiki.get_page("/cafe").expect("decoded page");
+        assert_eq!(page.title(), "Café");
+        assert_eq!(page.markdown_raw, "Voilà, naïve résumé");
+    }
+
+    #[cfg(not(feature = "encoding_rs"))]
+    #[test]
+    fn test_read_latin1_page_without_fallback() {
+        let dir = TempDir::new("latin1").expect("create temp dir");
+        write_file(&dir, "cafe.md", b"Caf\xe9");
+        assert!(Page::from_file(dir.path().to_path_buf(), dir.path().join("cafe.md")).is_err());
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert!(wiki.is_empty());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_new_async() {
+        let dir = TempDir::new("async").expect("create temp dir");
+        write_file(&dir, "one.md", b"---\ntitle: One\n---\n# One");
+        write_file(&dir, "two.md", b"[[one]]");
+        let runtime = tokio::runtime::Builder::new_current_thread().build().expect("runtime");
+        let wiki = runtime.block_on(Wiki::new_async(dir.path().to_str().unwrap()))
+            .expect("load wiki");
+        let sync = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.urls(), sync.urls());
+        for page in sync.iter() {
+            let loaded = wiki.get_page(page.url.as_str()).unwrap();
+            assert_eq!(loaded.title(), page.title());
+            assert_eq!(loaded.html(), page.html());
+        }
+
+        let missing = dir.path().join("missing");
+        assert!(runtime.block_on(Wiki::new_async(missing.to_str().unwrap())).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_info_serde() {
+        extern crate serde_json;
+        let page = make_page("info", "---\ntitle: Info\ntags: [a, b]\n---\nThe summary.");
+        let info = page.info();
+        let json: serde_json::Value = serde_json::to_value(&info).expect("serialize info");
+        assert_eq!(json["url"], "/info");
+        assert_eq!(json["title"], "Info");
+        assert_eq!(json["tags"], serde_json::json!(["a", "b"]));
+        assert_eq!(json["summary"], "The summary.");
+        assert!(json["last_modified"].is_object());
+
+        let parsed: super::PageInfo = serde_json::from_value(json).expect("deserialize info");
+        assert_eq!(parsed, info);
+    }
+
+    /// Creates a wiki with pages whose URLs, titles and modification times
+    /// are all ordered differently
+    fn make_list_wiki(dir: &TempDir) -> Wiki {
+        let now = SystemTime::now();
+        for &(name, title, age) in &[("a", "Zebra", 60), ("b", "apple", 0), ("c", "Mango", 120)] {
+            write_file(dir, &format!("{}.md", name), format!("# {}", title).as_bytes());
+            fs::File::options().write(true).open(dir.path().join(format!("{}.md", name)))
+                .expect("open file")
+                .set_modified(now - Duration::from_secs(age))
+                .expect("set mtime");
+        }
+        Wiki::new(dir.path().to_str().unwrap()).expect("load wiki")
+    }
+
+    fn page_urls(pages: Vec<&Page>) -> Vec<&str> {
+        pages.into_iter().map(|page| page.url.as_str()).collect()
+    }
+
+    #[test]
+    fn test_page_list_sort_keys() {
+        let dir = TempDir::new("page_list").expect("create temp dir");
+        let wiki = make_list_wiki(&dir);
+        assert_eq!(page_urls(wiki.page_list(0, 10, SortKey::Url)), vec!["/a", "/b", "/c"]);
+        assert_eq!(page_urls(wiki.page_list(0, 10, SortKey::Title)), vec!["/b", "/c", "/a"]);
+        assert_eq!(page_urls(wiki.page_list(0, 10, SortKey::ModifiedDesc)), vec!["/b", "/a", "/c"]);
+    }
+
+    #[test]
+    fn test_page_list_window() {
+        let dir = TempDir::new("page_list_window").expect("create temp dir");
+        let wiki = make_list_wiki(&dir);
+        assert_eq!(page_urls(wiki.page_list(1, 1, SortKey::Url)), vec!["/b"]);
+        assert_eq!(page_urls(wiki.page_list(2, 5, SortKey::Url)), vec!["/c"]);
+        assert!(wiki.page_list(3, 5, SortKey::Url).is_empty());
+        assert!(wiki.page_list(100, 5, SortKey::Title).is_empty());
+        assert!(wiki.page_list(0, 0, SortKey::Url).is_empty());
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let patterns = ["_*", "node_modules/**", "/README.md", "drafts/*.md"];
+        assert!(super::is_excluded("_template.md", &patterns));
+        assert!(super::is_excluded("docs/_partial.md", &patterns));
+        assert!(super::is_excluded("_layouts/page.md", &patterns));
+        assert!(super::is_excluded("node_modules/pkg/docs/readme.md", &patterns));
+        assert!(super::is_excluded("README.md", &patterns));
+        assert!(super::is_excluded("drafts/idea.md", &patterns));
+        assert!(!super::is_excluded("docs/README.md", &patterns));
+        assert!(!super::is_excluded("drafts/old/idea.md", &patterns));
+        assert!(!super::is_excluded("guide.md", &patterns));
+        assert!(super::is_excluded("a/b/c.md", &["a/**/c.md"]));
+        assert!(super::is_excluded("a/c.md", &["a/**/c.md"]));
+        assert!(super::is_excluded("notes/1.md", &["notes/?.md"]));
+    }
+
+    #[test]
+    fn test_exclude() {
+        let dir = TempDir::new("exclude").expect("create temp dir");
+        write_file(&dir, "_template.md", b"# {{title}}");
+        write_file(&dir, "guide.md", b"# Guide");
+        write_file(&dir, "node_modules/pkg/readme.md", b"# Readme");
+        let wiki = Wiki::builder().path(dir.path()).exclude(&["_*", "node_modules/**"])
+            .build().expect("load wiki");
+        assert_eq!(wiki.urls(), vec!["/guide"]);
+        assert!(wiki.watched_page(dir.path().join("_template.md").as_path()).is_none());
+        assert_eq!(Wiki::new(dir.path().to_str().unwrap()).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let dir = TempDir::new("max_depth").expect("create temp dir");
+        write_file(&dir, "top.md", b"top");
+        write_file(&dir, "a/middle.md", b"middle");
+        write_file(&dir, "a/b/deep.md", b"deep");
+        let wiki = Wiki::builder().path(dir.path()).max_depth(Some(2)).build().expect("load wiki");
+        assert_eq!(wiki.urls(), vec!["/a/middle", "/top"]);
+        assert!(wiki.watched_page(dir.path().join("a").join("b").join("deep.md").as_path())
+            .is_none());
+        let wiki = Wiki::builder().path(dir.path()).max_depth(Some(1)).build().expect("load wiki");
+        assert_eq!(wiki.urls(), vec!["/top"]);
+        assert_eq!(Wiki::new(dir.path().to_str().unwrap()).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_new_multi() {
+        let core = TempDir::new("multi_core").expect("create temp dir");
+        write_file(&core, "index.md", b"# Core");
+        write_file(&core, "guide.md", b"core guide");
+        let plugin = TempDir::new("multi_plugin").expect("create temp dir");
+        write_file(&plugin, "guide.md", b"plugin guide");
+        write_file(&plugin, "plugin/setup.md", b"[[guide]]");
+
+        let paths = [core.path().to_str().unwrap(), plugin.path().to_str().unwrap()];
+        let mut wiki = Wiki::new_multi(&paths).expect("load wiki");
+        assert_eq!(wiki.urls(), vec!["/guide", "/index", "/plugin/setup"]);
+        let guide = wiki.get_page("/guide").unwrap();
+        assert_eq!(guide.markdown_raw, "plugin guide");
+        assert_eq!(guide.path, plugin.path().join("guide.md"));
+        assert_eq!(wiki.get_page("/").unwrap().title(), "Core");
+        assert_eq!(wiki.backlinks("/guide").len(), 1);
+
+        wiki.reload().expect("reload wiki");
+        assert_eq!(wiki.len(), 3);
+        assert_eq!(wiki.get_page("/guide").unwrap().markdown_raw, "plugin guide");
+        assert!(Wiki::new_multi(&[]).is_err());
+        let missing = core.path().join("missing");
+        assert!(Wiki::new_multi(&[paths[0], missing.to_str().unwrap()]).is_err());
+    }
+
+    #[test]
+    fn test_new_multi_rename() {
+        let core = TempDir::new("multi_rename_core").expect("create temp dir");
+        write_file(&core, "index.md", b"# Core");
+        let plugin = TempDir::new("multi_rename_plugin").expect("create temp dir");
+        write_file(&plugin, "plug.md", b"plug");
+        write_file(&plugin, "tree/leaf.md", b"leaf");
+
+        let paths = [core.path().to_str().unwrap(), plugin.path().to_str().unwrap()];
+        let mut wiki = Wiki::new_multi(&paths).expect("load wiki");
+        wiki.rename_page("/plug", "/plug2").expect("rename page");
+        let page = wiki.get_page("/plug2").unwrap();
+        assert_eq!(page.path, plugin.path().join("plug2.md"));
+        assert_eq!(page.base_path, plugin.path());
+        assert!(!core.path().join("plug2.md").exists());
+
+        assert_eq!(wiki.move_subtree("/tree", "/moved").expect("move subtree"), 1);
+        assert!(plugin.path().join("moved").join("leaf.md").exists());
+        assert!(!plugin.path().join("tree").exists());
+        assert!(!core.path().join("moved").exists());
+
+        let wiki = Wiki::new_multi(&paths).expect("reload wiki");
+        assert_eq!(wiki.urls(), vec!["/index", "/moved/leaf", "/plug2"]);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(super::levenshtein("kitten", "sitting"), 3);
+        assert_eq!(super::levenshtein("", "abc"), 3);
+        assert_eq!(super::levenshtein("same", "same"), 0);
+        assert_eq!(super::levenshtein("/gude", "/guide"), 1);
+    }
+
+    #[test]
+    fn test_get_page_url_matching() {
+        let dir = TempDir::new("url_matching").expect("create temp dir");
+        write_file(&dir, "docs/guide.md", b"guide");
+        write_file(&dir, "docs/guides.md", b"guides");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert!(wiki.get_page("/Docs/Guide").is_none());
+
+        wiki.url_matching = UrlMatching::CaseInsensitive;
+        assert_eq!(wiki.get_page("/Docs/Guide").map(|p| p.url.as_str()), Some("/docs/guide"));
+        assert!(wiki.get_page("/docs/gude").is_none());
+
+        wiki.url_matching = UrlMatching::Fuzzy(2);
+        assert_eq!(wiki.get_page("/Docs/Gude").map(|p| p.url.as_str()), Some("/docs/guide"));
+        assert_eq!(wiki.get_page("/docs/guides").map(|p| p.url.as_str()), Some("/docs/guides"));
+        assert!(wiki.get_page("/docs/other").is_none());
+        assert_eq!(wiki.get_page_mut("/dcs/guid").map(|p| p.url.clone()).as_deref(),
+                   Some("/docs/guide"));
+    }
+
+    #[test]
+    fn test_links() {
+        let dir = TempDir::new("links").expect("create temp dir");
+        write_file(&dir, "docs/page.md", b"[inline](../other \"Other\") [web](https://example.com) \
+            [top](#top) ![logo](img/logo.png) [ref][guide] ![icon][] [[Wiki Page|wiki]]\n\n\
+            [guide]: guide.md\n[icon]: /icon.png\n");
+        let page = Page::from_file(dir.path().to_path_buf(), dir.path().join("docs").join("page.md"))
+            .expect("load page");
+        let text = |s: &str| String::from(s);
+        assert_eq!(page.links(), vec![
+            Link::Internal { url: text("/other"), text: text("inline") },
+            Link::External { url: text("https://example.com"), text: text("web") },
+            Link::Anchor { anchor: text("top"), text: text("top") },
+            Link::Image { src: text("img/logo.png"), alt: text("logo") },
+            Link::Internal { url: text("/docs/guide"), text: text("ref") },
+            Link::Image { src: text("/icon.png"), alt: text("icon") },
+            Link::Internal { url: text("/Wiki Page"), text: text("wiki") },
+        ]);
+    }
+
+    #[test]
+    fn test_code_classes() {
+        let dir = TempDir::new("code_classes").expect("create temp dir");
+        write_file(&dir, "code.md", b"```rust\nfn main() {}\n```\n\n```\nplain\n```\n");
+        let options = MarkdownOptions { code_classes: true, ..MarkdownOptions::default() };
+        let wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        assert_eq!(
+            wiki.get_page("/code").unwrap().html(),
+            "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n\n\
+             <pre><code class=\"language-plaintext\">plain\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let dir = TempDir::new("footnotes").expect("create temp dir");
+        write_file(&dir, "notes.md", b"First[^first] and second[^2].\n\n\
+                                       [^first]: The *first* note.\n[^2]: The second note.\n");
+        let options = MarkdownOptions { footnotes: true, ..MarkdownOptions::default() };
+        let wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        let html = wiki.get_page("/notes").unwrap().html();
+        assert!(html.starts_with(
+            "<p>First<sup id=\"fnref1\"><a href=\"#fn1\" class=\"footnote-ref\">1</a></sup> \
+             and second<sup id=\"fnref2\"><a href=\"#fn2\" class=\"footnote-ref\">2</a></sup>.</p>"
+        ));
+        let first = html.find("<li id=\"fn1\">\n<p>The <em>first</em> note.&nbsp;\
+                               <a href=\"#fnref1\" class=\"footnote-backref\">&#8617;</a></p>")
+            .expect("first footnote");
+        let second = html.find("<li id=\"fn2\">\n<p>The second note.").expect("second footnote");
+        assert!(html.contains("<div class=\"footnotes\">\n<hr>\n<ol>"));
+        assert!(first < second);
+        assert!(!html.contains("rel=") && !html.contains("rev="));
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert!(wiki.get_page("/notes").unwrap().html().contains("[^first]: The"));
+    }
+
+    #[test]
+    fn test_emoji() {
+        let dir = TempDir::new("emoji").expect("create temp dir");
+        write_file(&dir, "page.md", b"Shipped :rocket: but `:bug:`");
+        let options = MarkdownOptions { emoji: true, ..MarkdownOptions::default() };
+        let wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        let page = wiki.get_page("/page").unwrap();
+        assert_eq!(page.html(), "<p>Shipped \u{1F680} but <code>:bug:</code></p>\n");
+        assert_eq!(page.markdown_raw, "Shipped :rocket: but `:bug:`");
+    }
+
+    #[test]
+    fn test_definition_lists() {
+        let dir = TempDir::new("definition_lists").expect("create temp dir");
+        write_file(&dir, "glossary.md", b"# Glossary\n\nWiki\n: a website edited by its users\n\
+                                          : *Hawaiian* for quick\n");
+        let options = MarkdownOptions { definition_lists: true, sanitize: true,
+                                        ..MarkdownOptions::default() };
+        let wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        assert_eq!(wiki.get_page("/glossary").unwrap().html(),
+                   "<h1 id=\"glossary\">Glossary</h1>\n\n<dl>\n<dt>Wiki</dt>\n\
+                    <dd>a website edited by its users</dd>\n<dd><em>Hawaiian</em> for quick</dd>\n</dl>\n");
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert!(wiki.get_page("/glossary").unwrap().html().contains("<p>Wiki\n: a website"));
+    }
+
+    #[test]
+    fn test_render_all() {
+        let dir = TempDir::new("render_all").expect("create temp dir");
+        write_file(&dir, "table.md", b"| a | b |\n|---|---|\n| 1 | 2 |\n");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert!(!wiki.get_page("/table").unwrap().html().contains("<table>"));
+
+        wiki.markdown_options.extensions = TABLES;
+        assert!(!wiki.get_page("/table").unwrap().html().contains("<table>"));
+        wiki.render_all();
+        let page = wiki.get_page("/table").unwrap();
+        assert!(page.html().contains("<table>"));
+        assert_eq!(page.markdown_options.extensions, TABLES);
+        assert!(!page.is_dirty());
+    }
+
+    #[test]
+    fn test_render_all_missing_links() {
+        let dir = TempDir::new("render_all_missing").expect("create temp dir");
+        write_file(&dir, "page.md", b"[[missing]] and [[page]]");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert!(!wiki.get_page("/page").unwrap().html().contains("class=\"missing\""));
+
+        wiki.markdown_options.mark_missing_links = true;
+        wiki.render_all();
+        assert_eq!(wiki.get_page("/page").unwrap().html(),
+                   "<p><a href=\"/missing\" class=\"missing\">missing</a> and \
+                    <a href=\"/page\">page</a></p>\n");
+
+        wiki.markdown_options.mark_missing_links = false;
+        wiki.render_all();
+        assert!(!wiki.get_page("/page").unwrap().html().contains("class=\"missing\""));
+    }
+
+    #[test]
+    fn test_drafts() {
+        let dir = TempDir::new("drafts").expect("create temp dir");
+        write_file(&dir, "published.md", b"---\ndraft: false\ntags: [a]\n---\nsecret plans");
+        write_file(&dir, "draft.md", b"---\ndraft: true\ntags: [a]\n---\nsecret plans");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        let draft = wiki.get_page("/draft").expect("draft page");
+        assert!(draft.is_draft());
+        assert!(!wiki.get_page("/published").unwrap().is_draft());
+        assert_eq!(wiki.urls(), vec!["/published"]);
+        assert_eq!(wiki.search("secret").len(), 1);
+        assert_eq!(wiki.pages_by_tag("a").len(), 1);
+        assert!(wiki.tree().child("draft").is_none());
+        assert_eq!(wiki.page_list(0, 10, SortKey::Url).len(), 1);
+        assert_eq!(wiki.sitemap_xml("https://example.com").matches("<url>").count(), 1);
+
+        let out = TempDir::new("drafts_out").expect("create temp dir");
+        wiki.export_static_site(out.path()).expect("export site");
+        assert!(!out.path().join("draft.html").exists());
+
+        wiki.include_drafts = true;
+        assert_eq!(wiki.urls(), vec!["/draft", "/published"]);
+        assert_eq!(wiki.search("secret").len(), 2);
+    }
+
+    #[test]
+    fn test_date() {
+        let date = |raw: &str| make_page("dated", raw).date();
+        assert_eq!(date("---\ndate: 2016-05-01\n---\n"), NaiveDate::from_ymd_opt(2016, 5, 1));
+        assert_eq!(date("---\ndate: 2016-05-01T12:30:00Z\n---\n"), NaiveDate::from_ymd_opt(2016, 5, 1));
+        assert_eq!(date("---\ndate: \"2016-05-01 12:30\"\n---\n"), NaiveDate::from_ymd_opt(2016, 5, 1));
+        assert_eq!(date("---\ndate: 2016-13-01\n---\n"), None);
+        assert_eq!(date("---\ndate: yesterday\n---\n"), None);
+        assert_eq!(date("no frontmatter"), None);
+    }
+
+    #[test]
+    fn test_pages_sorted_by_date() {
+        let dir = TempDir::new("dates").expect("create temp dir");
+        write_file(&dir, "old.md", b"---\ndate: 2015-01-01\n---\n");
+        write_file(&dir, "new.md", b"---\ndate: 2016-06-01\n---\n");
+        write_file(&dir, "middle.md", b"---\ndate: 2016-01-01\n---\n");
+        write_file(&dir, "broken.md", b"---\ndate: someday\n---\n");
+        write_file(&dir, "undated.md", b"undated");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        assert_eq!(page_urls(wiki.pages_sorted_by_date()),
+                   vec!["/new", "/middle", "/old", "/broken", "/undated"]);
+        // the page published on the cutoff date itself is not before it
+        let cutoff = NaiveDate::from_ymd_opt(2016, 1, 1).unwrap();
+        assert_eq!(page_urls(wiki.pages_published_before(cutoff)), vec!["/old"]);
+        let cutoff = NaiveDate::from_ymd_opt(2016, 1, 2).unwrap();
+        assert_eq!(page_urls(wiki.pages_published_before(cutoff)), vec!["/middle", "/old"]);
+    }
+
+    #[test]
+    fn test_meta_path() {
+        let page = make_page("nested", "---\ntitle: Nested\nauthor:\n  name: Jane\n  \
+                                        social:\n    web: example.com\nseo: plain\n---\n");
+        assert_eq!(page.meta_path("author.name").and_then(|v| v.as_str()), Some("Jane"));
+        assert_eq!(page.meta_path("author.social.web").and_then(|v| v.as_str()),
+                   Some("example.com"));
+        assert_eq!(page.meta_path("title").and_then(|v| v.as_str()), Some("Nested"));
+        assert!(page.meta_path("author").and_then(|v| v.as_hash()).is_some());
+        assert!(page.meta_path("author.email").is_none());
+        assert!(page.meta_path("seo.description").is_none());
+        assert!(page.meta_path("missing.name").is_none());
+        assert!(make_page("plain", "no frontmatter").meta_path("author.name").is_none());
+    }
+
+    #[test]
+    fn test_add_page() {
+        let dir = TempDir::new("add").expect("create temp dir");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let mut page = wiki.new_page("/drafts/idea").unwrap();
+        page.update_markdown("# Idea");
+        assert_eq!(wiki.add_page(page).expect("add page").url, "/drafts/idea");
+        assert_eq!(wiki.get_page("/drafts/idea").unwrap().markdown_raw, "# Idea");
+        assert!(wiki.get_page("/drafts/idea").unwrap().is_dirty());
+
+        let duplicate = wiki.new_page("/drafts/idea").unwrap();
+        match wiki.add_page(duplicate) {
+            Err(WikiError::AlreadyExists(url)) => assert_eq!(url, "/drafts/idea"),
+            _ => panic!("expected an AlreadyExists error")
+        }
+
+        let other = TempDir::new("other").expect("create temp dir");
+        let foreign = Wiki::new(other.path().to_str().unwrap()).unwrap().new_page("/foreign").unwrap();
+        match wiki.add_page(foreign) {
+            Err(WikiError::InvalidUrl(url)) => assert_eq!(url, "/foreign"),
+            _ => panic!("expected an InvalidUrl error")
+        }
+        assert!(wiki.get_page("/foreign").is_none());
+    }
+
+    #[test]
+    fn test_new_page_in_memory() {
+        let dir = TempDir::new("memory").expect("create temp dir");
+        let mut meta = yaml_rust::yaml::Hash::new();
+        meta.insert(Yaml::String(String::from("title")), Yaml::String(String::from("Preview")));
+        let mut page = Page::new(dir.path().to_path_buf(), "notes/preview/", "*draft*",
+                                 Some(Yaml::Hash(meta))).expect("create page");
+        assert_eq!(page.url, "/notes/preview");
+        assert_eq!(page.path, dir.path().join("notes").join("preview.md"));
+        assert_eq!(page.raw, "");
+        assert_eq!(page.title(), "Preview");
+        assert_eq!(page.html(), "<p><em>draft</em></p>\n");
+        assert!(page.is_dirty());
+        assert!(!page.path.exists());
+
+        fs::create_dir(dir.path().join("notes")).expect("create directory");
+        page.save_to_file().expect("save page");
+        assert!(!page.is_dirty());
+        let saved = Page::from_file(dir.path().to_path_buf(), page.path.clone())
+            .expect("read page");
+        assert_eq!(saved.url, "/notes/preview");
+        assert_eq!(saved.title(), "Preview");
+        assert_eq!(saved.markdown_raw, "*draft*\n");
+        assert_eq!(page.raw, saved.raw);
+    }
+
+    #[test]
+    fn test_new_page_in_memory_traversal() {
+        let dir = TempDir::new("memory_traversal").expect("create temp dir");
+        for url in &["/../x", "a/./..\\b/c", "", "/"] {
+            match Page::new(dir.path().to_path_buf(), url, "", None) {
+                Err(WikiError::InvalidUrl(invalid)) => assert_eq!(invalid, *url),
+                _ => panic!("expected {:?} to be rejected", url)
+            }
+        }
+        let page = Page::new(dir.path().to_path_buf(), "docs/page/", "text", None)
+            .expect("create page");
+        assert_eq!(page.url, "/docs/page");
+        assert_eq!(page.path, dir.path().join("docs").join("page.md"));
+    }
+
+    #[test]
+    fn test_read_page_with_bom() {
+        let page = make_page("bom", "\u{feff}---\ntitle: Byte Order\n---\n# Heading");
+        assert_eq!(page.title(), "Byte Order");
+        assert_eq!(page.markdown_raw.trim(), "# Heading");
+        assert!(!page.raw.starts_with('\u{feff}'));
+    }
+
+    #[test]
+    fn test_frontmatter_formats_round_trip() {
+        let dir = TempDir::new("formats").expect("create temp dir");
+        write_file(&dir, "yaml.md", b"---\ntitle: Yaml\n---\nyaml body");
+        write_file(&dir, "toml.md", b"+++\ntitle = \"Toml\"\ntags = [\"a\"]\n+++\ntoml body");
+        let mut wiki = Wiki::builder()
+            .path(dir.path().to_str().unwrap())
+            .frontmatter_format(FrontmatterFormat::Toml)
+            .build()
+            .expect("load wiki");
+
+        for url in &["/yaml", "/toml"] {
+            let mut page = wiki.get_page_mut(url).unwrap();
+            page.set_meta("weight", Yaml::Integer(2));
+            page.save_to_file().expect("save page");
+        }
+        let mut page = wiki.new_page("/new").unwrap();
+        page.set_meta("title", Yaml::String(String::from("New")));
+        page.update_markdown("new body");
+        page.save_to_file().expect("save page");
+
+        assert_eq!(fs::read_to_string(dir.path().join("yaml.md")).unwrap(),
+                   "---\ntitle: Yaml\nweight: 2\n---\nyaml body\n");
+        assert_eq!(fs::read_to_string(dir.path().join("toml.md")).unwrap(),
+                   "+++\ntags = [\"a\"]\ntitle = \"Toml\"\nweight = 2\n+++\ntoml body\n");
+        assert_eq!(fs::read_to_string(dir.path().join("new.md")).unwrap(),
+                   "+++\ntitle = \"New\"\n+++\nnew body\n");
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let toml = wiki.get_page("/toml").unwrap();
+        assert_eq!(toml.frontmatter_format, FrontmatterFormat::Toml);
+        assert_eq!(toml.title(), "Toml");
+        assert_eq!(toml.tags(), vec!["a"]);
+        assert_eq!(toml.get_meta("weight").and_then(|w| w.as_i64()), Some(2));
+        assert_eq!(toml.markdown_raw, "toml body\n");
+        assert_eq!(wiki.get_page("/new").unwrap().title(), "New");
+        assert_eq!(wiki.get_page("/yaml").unwrap().frontmatter_format, FrontmatterFormat::Yaml);
+    }
+
+    #[test]
+    fn test_save_keeps_frontmatter_text() {
+        let dir = TempDir::new("verbatim").expect("create temp dir");
+        let frontmatter = "---\n# shown in the sidebar\ntitle:   Verbatim\ntags: [b, a]\n\
+                           author: me\n---\n";
+        write_file(&dir, "page.md", format!("{}old body", frontmatter).as_bytes());
+        let mut page = Page::from_file(dir.path().to_path_buf(), dir.path().join("page.md"))
+            .expect("read page");
+
+        page.set_meta("author", Yaml::String(String::from("me")));
+        assert!(!page.is_dirty());
+        page.update_markdown("new body");
+        page.save_to_file().expect("save page");
+        assert_eq!(fs::read_to_string(dir.path().join("page.md")).unwrap(),
+                   format!("{}new body\n", frontmatter));
+
+        page.set_meta("author", Yaml::String(String::from("you")));
+        page.save_to_file().expect("save page");
+        let saved = fs::read_to_string(dir.path().join("page.md")).unwrap();
+        assert!(!saved.contains("# shown in the sidebar"));
+        assert!(saved.contains("author: you"));
+    }
+
+    #[test]
+    fn test_save_replaces_file_atomically() {
+        let dir = TempDir::new("atomic").expect("create temp dir");
+        let old = "old ".repeat(50_000);
+        write_file(&dir, "page.md", old.as_bytes());
+        let path = dir.path().join("page.md");
+        let mut page = Page::from_file(dir.path().to_path_buf(), path.clone())
+            .expect("read page");
+        let new = "new\n".repeat(50_000);
+        page.update_markdown(new.as_str());
+
+        let reader_path = path.clone();
+        let reader = thread::spawn(move || {
+            for _ in 0..200 {
+                let contents = fs::read_to_string(&reader_path).expect("read page");
+                assert!(contents.len() == 200_000, "observed a partially written page");
+            }
+        });
+        page.save_to_file().expect("save page");
+        reader.join().expect("reader saw complete pages");
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), new);
+        let files: Vec<_> = fs::read_dir(dir.path()).unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, vec!["page.md"]);
+    }
+
+    #[test]
+    fn test_len() {
+        let dir = TempDir::new("len").expect("create temp dir");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.len(), 0);
+        assert!(wiki.is_empty());
+
+        write_file(&dir, "one.md", b"one");
+        fs::create_dir(dir.path().join("sub")).expect("create directory");
+        write_file(&dir, "sub/two.md", b"---\ndraft: true\n---\ntwo");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.len(), 2);
+        assert!(!wiki.is_empty());
+    }
+
+    #[test]
+    fn test_contains() {
+        let dir = TempDir::new("contains").expect("create temp dir");
+        fs::create_dir(dir.path().join("docs")).expect("create directory");
+        write_file(&dir, "page.md", b"page");
+        write_file(&dir, "docs/index.md", b"docs");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        for url in &["/page", "page", "//page/", "/docs", "docs/", "/docs/index"] {
+            assert!(wiki.contains(url), "{} should exist", url);
+        }
+        for url in &["/missing", "/Page", "/docs/page", "/", ""] {
+            assert!(!wiki.contains(url), "{} should not exist", url);
+        }
+
+        let fuzzy = Wiki::builder()
+            .path(dir.path().to_str().unwrap())
+            .url_matching(UrlMatching::CaseInsensitive)
+            .build()
+            .expect("load wiki");
+        assert!(fuzzy.contains("/PAGE"));
+    }
+
+    #[test]
+    fn test_search_plain_text() {
+        let dir = TempDir::new("search").expect("create temp dir");
+        write_file(&dir, "link.md", b"read [the manual](https://example.com/rust) first");
+        write_file(&dir, "bold.md", b"a **very** important note");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.get_page("/link").unwrap().plain_text(), "read the manual first");
+        assert!(wiki.search("rust").is_empty());
+        assert!(wiki.search("**").is_empty());
+        assert_eq!(page_urls(wiki.search("the manual")), vec!["/link"]);
+        assert_eq!(page_urls(wiki.search("very important")), vec!["/bold"]);
+    }
+
+    #[test]
+    fn test_heading_offset() {
+        let dir = TempDir::new("offset").expect("create temp dir");
+        write_file(&dir, "page.md", b"# Title\n\n## Section\n\n###### Deep");
+        let options = MarkdownOptions { heading_offset: 1, ..MarkdownOptions::default() };
+        let wiki = Wiki::with_markdown_options(dir.path().to_str().unwrap(), options)
+            .expect("load wiki");
+        assert_eq!(
+            wiki.get_page("/page").unwrap().html(),
+            "<h2 id=\"title\">Title</h2>\n\n<h3 id=\"section\">Section</h3>\n\n\
+             <h6 id=\"deep\">Deep</h6>\n"
+        );
+    }
+
+    #[test]
+    fn test_move_subtree() {
+        let dir = TempDir::new("subtree").expect("create temp dir");
+        fs::create_dir_all(dir.path().join("old").join("deep")).expect("create directories");
+        write_file(&dir, "old.md", b"old");
+        write_file(&dir, "old/one.md", b"one");
+        write_file(&dir, "old/deep/two.md", b"two");
+        write_file(&dir, "older.md", b"not moved");
+        write_file(&dir, "taken.md", b"taken");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        assert_eq!(wiki.move_subtree("/old/", "/new/place").expect("move subtree"), 3);
+        assert_eq!(wiki.urls(), vec!["/new/place", "/new/place/deep/two", "/new/place/one",
+                                     "/older", "/taken"]);
+        assert_eq!(wiki.get_page("/new/place/deep/two").unwrap().markdown_raw, "two");
+        assert!(dir.path().join("new").join("place").join("deep").join("two.md").is_file());
+        assert!(dir.path().join("new").join("place.md").is_file());
+        assert!(!dir.path().join("old").exists());
+        assert!(!dir.path().join("old.md").exists());
+
+        assert_eq!(wiki.move_subtree("/missing", "/elsewhere").expect("move nothing"), 0);
+        match wiki.move_subtree("/new", "/new/place/inside") {
+            Err(WikiError::InvalidUrl(_)) => (),
+            _ => panic!("expected an InvalidUrl error")
+        }
+        wiki.create_page("/taken/deep/two", "taken").expect("create page");
+        match wiki.move_subtree("/new/place", "/taken") {
+            Err(WikiError::AlreadyExists(url)) => assert_eq!(url, "/taken"),
+            _ => panic!("expected an AlreadyExists error")
+        }
+        assert!(wiki.contains("/new/place/one"));
+
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.len(), 6);
+    }
+
+    #[test]
+    fn test_anchors() {
+        let mut page = make_page("anchors", "# Usage\n\n## Install\n\nUsage\n-----\n\n\
+                                             ```\n# not a heading\n```\n\n## Usage\n\n### Hello, World!");
+        page.markdown_options.extensions = FENCED_CODE;
+        assert_eq!(page.anchors(), vec!["usage", "install", "usage-1", "usage-2", "hello-world"]);
+        for anchor in page.anchors() {
+            assert!(page.html().contains(format!("id=\"{}\"", anchor).as_str()));
+        }
+        assert!(make_page("plain", "no headings").anchors().is_empty());
+    }
+
+    #[test]
+    fn test_validate_fragment_links() {
+        let dir = TempDir::new("fragments").expect("create temp dir");
+        write_file(&dir, "guide.md", b"# Setup\n\n## Usage\n\n## Usage");
+        write_file(&dir, "index.md", b"[a](guide#setup) [b](/guide#usage-1) [c](guide#missing) \
+                                        [d](#local) [e](#nowhere)\n\n## Local");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        // a link checker built on top of the anchors of the pages
+        let page = wiki.get_page("/index").unwrap();
+        let broken: Vec<String> = super::markdown::inline_links(page.markdown_raw.as_str())
+            .into_iter()
+            .filter_map(|link| {
+                let mut parts = link.target.splitn(2, '#');
+                let target = parts.next().unwrap();
+                let fragment = parts.next()?;
+                let linked = if target.is_empty() {
+                    page
+                } else {
+                    wiki.get_page(super::resolve_link(page.url.as_str(), target)?.as_str())?
+                };
+                if linked.anchors().iter().any(|anchor| anchor == fragment) {
+                    None
+                } else {
+                    Some(link.target)
+                }
+            })
+            .collect();
+        assert_eq!(broken, vec!["guide#missing", "#nowhere"]);
+    }
+
+    #[test]
+    fn test_stats() {
+        let dir = TempDir::new("stats").expect("create temp dir");
+        write_file(&dir, "one.md", b"---\ntags: [rust, wiki]\n---\none two three [[missing]]");
+        write_file(&dir, "two.md", b"---\ntags: rust\ndraft: true\n---\nfour [five](/one)");
+        write_file(&dir, "three.md", b"[gone](/gone) [also gone](nope) [[missing]]");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.stats(), WikiStats {
+            pages: 3,
+            words: 10,
+            drafts: 1,
+            tags: 2,
+            broken_links: 4,
+        });
+
+        let empty = TempDir::new("stats").expect("create temp dir");
+        let wiki = Wiki::new(empty.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(wiki.stats(), WikiStats { pages: 0, words: 0, drafts: 0, tags: 0, broken_links: 0 });
+    }
+
+    #[test]
+    fn test_orphaned_pages() {
+        let dir = TempDir::new("orphans").expect("create temp dir");
+        write_file(&dir, "index.md", b"[docs](docs) [[about]]");
+        fs::create_dir(dir.path().join("docs")).expect("create directory");
+        write_file(&dir, "docs/index.md", b"[guide](./guide)");
+        write_file(&dir, "docs/guide.md", b"[home](/) [[docs/guide|itself]]");
+        write_file(&dir, "about.md", b"[guide](/docs/guide)");
+        write_file(&dir, "lonely.md", b"[[lonely|only itself]] [about](/about)");
+        let wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        assert_eq!(page_urls(wiki.orphaned_pages()), vec!["/lonely"]);
+
+        let wiki = Wiki::builder().path(dir.path()).home_page("/lonely").build().expect("load wiki");
+        assert!(wiki.orphaned_pages().is_empty());
+
+        let dir = TempDir::new("orphans_home").expect("create temp dir");
+        write_file(&dir, "index.md", b"[[start]]");
+        write_file(&dir, "start.md", b"start");
+        let wiki = Wiki::builder().path(dir.path()).home_page("/start").build().expect("load wiki");
+        assert_eq!(page_urls(wiki.orphaned_pages()), vec!["/index"]);
+    }
+
+    #[test]
+    fn test_reload_reuses_unchanged_pages() {
+        let dir = TempDir::new("cache").expect("create temp dir");
+        write_file(&dir, "same.md", b"# Same");
+        write_file(&dir, "changed.md", b"# Before");
+        write_file(&dir, "touched.md", b"# Touched");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+        let same = wiki.get_page("/same").unwrap().html().as_ptr();
+        let touched = wiki.get_page("/touched").unwrap().html().as_ptr();
+
+        write_file(&dir, "changed.md", b"# After");
+        write_file(&dir, "touched.md", b"# Touched");
+        wiki.reload().expect("reload wiki");
+        assert_eq!(wiki.get_page("/same").unwrap().html().as_ptr(), same);
+        assert_eq!(wiki.get_page("/touched").unwrap().html().as_ptr(), touched);
+        assert_eq!(wiki.get_page("/changed").unwrap().html(), "<h1 id=\"after\">After</h1>\n");
+
+        // unsaved modifications are dropped even though the file is the same
+        wiki.get_page_mut("/same").unwrap().update_markdown("# Unsaved");
+        wiki.get_page_mut("/same").unwrap().reload().expect("reload page");
+        assert_eq!(wiki.get_page("/same").unwrap().markdown_raw, "# Same");
+
+        // saved pages are reused as well
+        let mut page = wiki.get_page_mut("/changed").unwrap();
+        page.update_markdown("# Saved");
+        page.save_to_file().expect("save page");
+        let saved = page.html().as_ptr();
+        drop(page);
+        wiki.reload().expect("reload wiki");
+        assert_eq!(wiki.get_page("/changed").unwrap().html().as_ptr(), saved);
+
+        // changing the options renders all pages again
+        wiki.markdown_options.heading_offset = 1;
+        wiki.reload().expect("reload wiki");
+        assert_eq!(wiki.get_page("/same").unwrap().html(), "<h2 id=\"same\">Same</h2>\n");
+    }
+
+    #[test]
+    fn test_render_with() {
+        let page = make_page("custom", "# Title\n\n<b>raw</b> and a\nbreak\n\n| a |\n|---|\n| 1 |\n");
+        let default = String::from(page.html());
+        assert!(default.contains("<h1 id=\"title\">"));
+        assert!(default.contains("<b>raw</b>"));
+        assert!(!default.contains("<table>"));
+
+        let custom = page.render_with(html::ESCAPE | html::HARD_WRAP, TABLES);
+        assert!(custom.contains("<h1>Title</h1>"));
+        assert!(custom.contains("&lt;b&gt;raw&lt;/b&gt;"));
+        assert!(custom.contains("a<br>\nbreak"));
+        assert!(custom.contains("<table>"));
+        assert_eq!(page.html(), default);
+        assert_eq!(make_page("empty", "").render_with(html::Flags::empty(), Extension::empty()), "");
+    }
+
+    #[test]
+    fn test_aliases() {
+        let dir = TempDir::new("aliases").expect("create temp dir");
+        write_file(&dir, "new-name.md", b"---\naliases: [/old-name, older/name/]\n---\nmoved");
+        write_file(&dir, "single.md", b"---\naliases: previous\n---\nsingle");
+        write_file(&dir, "taken.md", b"---\naliases: [/single]\n---\ntaken");
+        write_file(&dir, "links.md", b"[old](/old-name) [[previous]]");
+        let mut wiki = Wiki::new(dir.path().to_str().unwrap()).expect("load wiki");
+
+        assert_eq!(wiki.get_page("/new-name").unwrap().aliases(), vec!["/old-name", "/older/name"]);
+        for url in &["/new-name", "/old-name", "old-name/", "/older/name"] {
+            assert_eq!(wiki.get_page(url).unwrap().url, "/new-name");
+        }
+        assert_eq!(wiki.get_page("/previous").unwrap().url, "/single");
+        // real urls take precedence over aliases
+        assert_eq!(wiki.get_page("/single").unwrap().markdown_raw, "single");
+        assert!(wiki.contains("/old-name"));
+        assert!(wiki.broken_links().is_empty());
+        assert!(!wiki.urls().contains(&"/old-name"));
+
+        let mut added = wiki.new_page("/added").unwrap();
+        added.set_meta("aliases", Yaml::Array(vec![Yaml::String(String::from("/added-before"))]));
+        wiki.add_page(added).expect("add page");
+        assert_eq!(wiki.get_page("/added-before").unwrap().url, "/added");
+        wiki.delete_page("/new-name").expect("delete page");
+        assert!(wiki.get_page("/old-name").is_none());
+
+        // aliases edited through `get_page_mut` are resolved right away
+        wiki.get_page_mut("/single").unwrap()
+            .set_meta("aliases", Yaml::Array(vec![Yaml::String(String::from("/renamed"))]));
+        assert_eq!(wiki.get_page("/renamed").unwrap().url, "/single");
+        assert!(wiki.get_page("/previous").is_none());
+    }
 }
@@ -0,0 +1,558 @@
+use std::cmp;
+
+use hoedown::{Extension, FENCED_CODE, FOOTNOTES, Markdown, Render};
+use hoedown::renderer::html::{self, Html};
+
+use super::feed::escape;
+use super::markdown::{Heading, WikiLink};
+
+/// Options that control how the markdown of a page is rendered to HTML
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarkdownOptions {
+    /// the hoedown extensions to enable, i.e. `TABLES` or `FENCED_CODE`
+    pub extensions: Extension,
+    /// escape any raw html contained in the markdown, use this if the
+    /// markdown comes from untrusted sources
+    pub sanitize: bool,
+    /// add `class="missing"` to wikilinks pointing to pages that do not
+    /// exist within the wiki
+    pub mark_missing_links: bool,
+    /// resolve relative link targets against the URL of the page, so the
+    /// HTML works regardless of where it is served from
+    pub resolve_relative_links: bool,
+    /// resolve relative image sources against the URL of the page
+    pub resolve_relative_images: bool,
+    /// add `language-*` classes derived from the info string of fenced
+    /// code blocks for client side highlighters, code blocks without a
+    /// language get `language-plaintext`. This implies `FENCED_CODE`
+    pub code_classes: bool,
+    /// the number of levels headings are moved down, i.e. `#` becomes
+    /// `<h2>` with an offset of 1. Headings never go below `<h6>`
+    pub heading_offset: u8,
+    /// render footnotes like `[^1]` as numbered references to a list at
+    /// the end of the page, numbered in the order they are referenced.
+    /// This implies `FOOTNOTES`
+    pub footnotes: bool,
+    /// replace emoji shortcodes like `:smile:` outside of code with the
+    /// emoji, the markdown of the page is left untouched
+    pub emoji: bool,
+    /// render paragraphs of terms followed by lines starting with `: ` as
+    /// definition lists, a term can have several definitions
+    pub definition_lists: bool,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> MarkdownOptions {
+        MarkdownOptions {
+            extensions: Extension::empty(),
+            sanitize: false,
+            mark_missing_links: false,
+            resolve_relative_links: false,
+            resolve_relative_images: false,
+            code_classes: false,
+            heading_offset: 0,
+            footnotes: false,
+            emoji: false,
+            definition_lists: false,
+        }
+    }
+}
+
+impl MarkdownOptions {
+    /// Returns the hoedown extensions that are enabled, including those
+    /// implied by the other options
+    pub fn all_extensions(&self) -> Extension {
+        let mut extensions = self.extensions;
+        if self.code_classes {
+            extensions = extensions | FENCED_CODE;
+        }
+        if self.footnotes {
+            extensions = extensions | FOOTNOTES;
+        }
+        extensions
+    }
+}
+
+/// Prepares a hoedown document for the given markdown using the options
+pub fn prepare(markdown: &str, options: &MarkdownOptions) -> Markdown {
+    Markdown::new(markdown).extensions(options.all_extensions())
+}
+
+/// Renders the given document to HTML. With `sanitize` links and images
+/// using a scheme other than `http`, `https` or `mailto` are neutralized
+/// as well, so `javascript:` or `data:` URLs can not be injected
+pub fn render_html(markdown: &Markdown, options: &MarkdownOptions) -> String {
+    if !options.sanitize {
+        return render_with_flags(markdown, html::Flags::empty());
+    }
+    let html = render_with_flags(markdown, html::ESCAPE);
+    let html = rewrite_links(html.as_str(), neutralize_unsafe_url);
+    rewrite_images(html.as_str(), neutralize_unsafe_url)
+}
+
+/// Returns `#` for URLs with a scheme other than `http`, `https` or
+/// `mailto`, relative URLs and URLs with a safe scheme are left untouched
+fn neutralize_unsafe_url(url: &str) -> Option<String> {
+    let scheme_end = url.find(':')?;
+    // a colon after the path, query or fragment started is no scheme
+    if url[..scheme_end].contains(['/', '?', '#']) {
+        return None;
+    }
+    // browsers ignore whitespace and control characters within the scheme
+    let scheme: String = url[..scheme_end].chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match scheme.as_str() {
+        "http" | "https" | "mailto" => None,
+        _ => Some(String::from("#"))
+    }
+}
+
+/// Renders the given document to HTML using the given renderer flags
+pub fn render_with_flags(markdown: &Markdown, flags: html::Flags) -> String {
+    // hoedown can not cope with an empty input buffer
+    if markdown.contents.is_empty() {
+        return String::new();
+    }
+    let mut html = Html::new(flags, 0);
+    String::from(html.render(markdown).to_str().unwrap())
+}
+
+/// Adds the given anchor ids to the headings of the rendered HTML, in the
+/// order the headings appear in the document
+pub fn add_heading_ids(html: &str, slugs: &[String]) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut slugs = slugs.iter();
+    let mut rest = html;
+    while let Some(start) = rest.find("<h") {
+        let tag = &rest[start..];
+        let bytes = tag.as_bytes();
+        let is_heading = bytes.len() >= 4
+            && bytes[2] >= b'1' && bytes[2] <= b'6'
+            && bytes[3] == b'>';
+        if !is_heading {
+            output.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            continue;
+        }
+        output.push_str(&rest[..start]);
+        match slugs.next() {
+            Some(slug) => output.push_str(
+                format!("<h{} id=\"{}\">", bytes[2] as char, slug).as_str()
+            ),
+            None => output.push_str(&tag[..4])
+        }
+        rest = &tag[4..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Returns the headings of the rendered HTML with their plain text, in the
+/// order `add_heading_ids` assigns the ids to them
+pub fn headings(html: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("<h") {
+        let bytes = &rest.as_bytes()[start..];
+        let is_heading = bytes.len() >= 4
+            && bytes[2] >= b'1' && bytes[2] <= b'6'
+            && bytes[3] == b'>';
+        if !is_heading {
+            rest = &rest[start + 2..];
+            continue;
+        }
+        rest = &rest[start + 4..];
+        let close = format!("</h{}>", bytes[2] as char);
+        let end = rest.find(close.as_str()).unwrap_or(rest.len());
+        headings.push(Heading { level: bytes[2] - b'0', text: text_content(&rest[..end]) });
+        rest = &rest[end..];
+    }
+    headings
+}
+
+/// Returns the text of an HTML fragment without any tags and with the
+/// entities hoedown escapes decoded
+fn text_content(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            continue;
+        }
+        if c == '&' {
+            if let Some(end) = rest.find(';') {
+                let decoded = match &rest[1..end] {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "nbsp" => Some('\u{a0}'),
+                    entity => entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X"))
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                        .and_then(char::from_u32)
+                };
+                if let Some(decoded) = decoded {
+                    text.push(decoded);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Moves all headings of the rendered HTML down by the given number of
+/// levels, clamping at `<h6>`
+pub fn shift_headings(html: &str, offset: u8) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let tag = &rest[start + 1..];
+        let name = tag.strip_prefix('/').unwrap_or(tag);
+        let bytes = name.as_bytes();
+        let is_heading = bytes.len() >= 3 && bytes[0] == b'h'
+            && bytes[1] >= b'1' && bytes[1] <= b'6'
+            && (bytes[2] == b'>' || bytes[2] == b' ');
+        if !is_heading {
+            output.push_str(&rest[..start + 1]);
+            rest = tag;
+            continue;
+        }
+        let level_at = start + 1 + (tag.len() - name.len()) + 1;
+        output.push_str(&rest[..level_at]);
+        let level = cmp::min(6, (bytes[1] - b'0').saturating_add(offset));
+        output.push((b'0' + level) as char);
+        rest = &rest[level_at + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Renders the entries of a table of contents, the level, the text and the
+/// anchor id of every heading, as a `<nav>` with nested lists following
+/// the levels of the headings
+pub fn toc_html(toc: &[(u8, String, String)]) -> String {
+    let mut html = String::from("<nav class=\"toc\">\n");
+    let mut levels: Vec<u8> = Vec::new();
+    for &(level, ref text, ref slug) in toc {
+        while levels.len() > 1 && level < *levels.last().unwrap() {
+            levels.pop();
+            html.push_str("</li>\n</ul>\n");
+        }
+        match levels.last() {
+            Some(&last) if level <= last => html.push_str("</li>\n<li>"),
+            Some(_) => {
+                levels.push(level);
+                html.push_str("\n<ul>\n<li>");
+            }
+            None => {
+                levels.push(level);
+                html.push_str("<ul>\n<li>");
+            }
+        }
+        html.push_str(format!("<a href=\"#{}\">{}</a>", slug, escape(text)).as_str());
+    }
+    for _ in levels {
+        html.push_str("</li>\n</ul>\n");
+    }
+    html.push_str("</nav>\n");
+    html
+}
+
+/// Replaces the paragraphs consisting of a table of contents marker like
+/// `[TOC]` in the rendered HTML with the given table of contents
+pub fn insert_toc(html: &str, toc: &str) -> String {
+    html.split_inclusive('\n').map(|line| {
+        let marker = line.trim_end().strip_prefix("<p>")
+            .and_then(|line| line.strip_suffix("</p>"))
+            .is_some_and(|text| {
+                text.eq_ignore_ascii_case("[toc]") || text.eq_ignore_ascii_case("[[toc]]")
+            });
+        if marker { toc } else { line }
+    }).collect()
+}
+
+/// Replaces the paragraphs in the rendered HTML that consist of one or more
+/// terms followed by definitions starting with `: ` with definition lists.
+/// Lines following a definition that do not start with `: ` continue it
+pub fn definition_lists(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<p>") {
+        let end = match rest[start..].find("</p>") {
+            Some(end) => start + end,
+            None => break
+        };
+        let list = definition_list(&rest[start + 3..end]);
+        match list {
+            Some(list) => {
+                // consecutive lists are merged into one
+                if output.ends_with("</dl>\n") && rest[..start].trim().is_empty() {
+                    let merged = output.len() - "</dl>\n".len();
+                    output.truncate(merged);
+                    output.push_str(list.trim_start_matches("<dl>\n"));
+                } else {
+                    output.push_str(&rest[..start]);
+                    output.push_str(list.as_str());
+                }
+                rest = rest[end + 4..].strip_prefix('\n').unwrap_or(&rest[end + 4..]);
+            }
+            None => {
+                output.push_str(&rest[..end + 4]);
+                rest = &rest[end + 4..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Renders the lines of a paragraph as a definition list, unless they are
+/// not made up of terms followed by definitions
+fn definition_list(paragraph: &str) -> Option<String> {
+    let lines: Vec<&str> = paragraph.lines().collect();
+    let terms = lines.iter().take_while(|line| !line.starts_with(": ")).count();
+    if terms == 0 || terms == lines.len() {
+        return None;
+    }
+    let mut list = String::from("<dl>\n");
+    for term in &lines[..terms] {
+        list.push_str(format!("<dt>{}</dt>\n", term.trim()).as_str());
+    }
+    let mut definitions: Vec<String> = Vec::new();
+    for line in &lines[terms..] {
+        match (line.strip_prefix(": "), definitions.last_mut()) {
+            (Some(definition), _) => definitions.push(String::from(definition.trim())),
+            (None, Some(definition)) => {
+                definition.push('\n');
+                definition.push_str(line.trim());
+            }
+            (None, None) => unreachable!("the first line after the terms is a definition")
+        }
+    }
+    for definition in definitions {
+        list.push_str(format!("<dd>{}</dd>\n", definition).as_str());
+    }
+    list.push_str("</dl>\n");
+    Some(list)
+}
+
+/// Marks the links to the given wikilink targets in the rendered HTML as
+/// missing if `exists` returns false for them
+pub fn mark_missing_links<F>(html: &str, links: &[WikiLink], exists: F) -> String
+    where F: Fn(&str) -> bool {
+    let mut html = String::from(html);
+    for link in links {
+        if exists(link.target.as_str()) {
+            continue;
+        }
+        let plain = format!("<a href=\"{}\">", link.href());
+        let marked = format!("<a href=\"{}\" class=\"missing\">", link.href());
+        html = html.replace(plain.as_str(), marked.as_str());
+    }
+    html
+}
+/// Calls `rewrite` with the value of every attribute starting with the
+/// given prefix, i.e. `<a href="`, and replaces the value with the result
+/// unless it is `None`
+fn rewrite_attributes<F>(html: &str, prefix: &str, rewrite: F) -> String
+    where F: Fn(&str) -> Option<String> {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(prefix) {
+        let value_start = start + prefix.len();
+        let value_end = match rest[value_start..].find('"') {
+            Some(end) => value_start + end,
+            None => break
+        };
+        output.push_str(&rest[..value_start]);
+        let value = &rest[value_start..value_end];
+        match rewrite(value) {
+            Some(rewritten) => output.push_str(rewritten.as_str()),
+            None => output.push_str(value)
+        }
+        rest = &rest[value_end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Replaces the targets of all links in the rendered HTML with the result
+/// of `rewrite`, targets for which it returns `None` are left untouched
+pub fn rewrite_links<F>(html: &str, rewrite: F) -> String
+    where F: Fn(&str) -> Option<String> {
+    rewrite_attributes(html, "<a href=\"", rewrite)
+}
+
+/// Replaces the sources of all images in the rendered HTML with the result
+/// of `rewrite`, sources for which it returns `None` are left untouched
+pub fn rewrite_images<F>(html: &str, rewrite: F) -> String
+    where F: Fn(&str) -> Option<String> {
+    rewrite_attributes(html, "<img src=\"", rewrite)
+}
+
+/// Replaces the `rel` and `rev` attributes hoedown puts on footnote links,
+/// which are no valid link types in HTML5, with classes
+pub fn fix_footnote_links(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<a href=\"#fn") {
+        let tag_end = match rest[start..].find('>') {
+            Some(end) => start + end + 1,
+            None => break
+        };
+        output.push_str(&rest[..start]);
+        let tag = &rest[start..tag_end];
+        output.push_str(tag.replace(" rel=\"footnote\"", " class=\"footnote-ref\"")
+            .replace(" rev=\"footnote\"", " class=\"footnote-backref\"").as_str());
+        rest = &rest[tag_end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Derives the language of a code block from the info string of the fence,
+/// i.e. `Rust`, `rust,ignore` or `{.rust}` all become `rust`
+fn code_language(info: &str) -> String {
+    let info = info.trim_start_matches(['{', '.']);
+    let language: String = info.chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '+' || *c == '-' || *c == '#' || *c == '_')
+        .collect();
+    if language.is_empty() {
+        String::from("plaintext")
+    } else {
+        language.to_lowercase()
+    }
+}
+
+/// Normalizes the language classes hoedown adds to fenced code blocks and
+/// adds `language-plaintext` to code blocks without a language
+pub fn add_code_classes(html: &str) -> String {
+    let html = rewrite_attributes(html, "<pre><code class=\"language-", |info| {
+        Some(code_language(info))
+    });
+    html.replace("<pre><code>", "<pre><code class=\"language-plaintext\">")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use hoedown::Markdown;
+    use super::{add_code_classes, add_heading_ids, definition_lists, headings, insert_toc, rewrite_links,
+                render_html, shift_headings, toc_html, MarkdownOptions};
+
+    #[test]
+    fn test_add_heading_ids() {
+        let slugs = vec![String::from("one"), String::from("two")];
+        assert_eq!(
+            add_heading_ids("<h1>One</h1>\n<hr>\n<p>x</p>\n<h3>Two</h3>\n<h2>Three</h2>", &slugs),
+            "<h1 id=\"one\">One</h1>\n<hr>\n<p>x</p>\n<h3 id=\"two\">Two</h3>\n<h2>Three</h2>"
+        )
+    }
+
+    #[test]
+    fn test_shift_headings() {
+        let html = "<h1 id=\"a\">A</h1>\n<hr>\n<h2>B</h2>\n<h5>C</h5>\n<h6>D</h6><head><p>h1</p>";
+        assert_eq!(
+            shift_headings(html, 1),
+            "<h2 id=\"a\">A</h2>\n<hr>\n<h3>B</h3>\n<h6>C</h6>\n<h6>D</h6><head><p>h1</p>"
+        );
+        assert_eq!(shift_headings(html, 0), html);
+        assert_eq!(shift_headings("<h1>A</h1>", u8::MAX), "<h6>A</h6>");
+        assert_eq!(shift_headings("a <ü <", 1), "a <ü <");
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let html = "<p><a href=\"a\">a</a> <a href=\"b\" title=\"B\">b</a> <img src=\"a\"></p>";
+        assert_eq!(
+            rewrite_links(html, |href| if href == "a" { Some(String::from("/x/a")) } else { None }),
+            "<p><a href=\"/x/a\">a</a> <a href=\"b\" title=\"B\">b</a> <img src=\"a\"></p>"
+        )
+    }
+
+    #[test]
+    fn test_add_code_classes() {
+        let html = "<pre><code class=\"language-Rust\">a</code></pre>\
+                    <pre><code class=\"language-{.python}\">b</code></pre>\
+                    <pre><code class=\"language-c++,ignore\">c</code></pre>\
+                    <pre><code>d</code></pre><p><code>e</code></p>";
+        assert_eq!(
+            add_code_classes(html),
+            "<pre><code class=\"language-rust\">a</code></pre>\
+             <pre><code class=\"language-python\">b</code></pre>\
+             <pre><code class=\"language-c++\">c</code></pre>\
+             <pre><code class=\"language-plaintext\">d</code></pre><p><code>e</code></p>"
+        )
+    }
+
+    #[test]
+    fn test_toc_html() {
+        let entry = |level: u8, text: &str| (level, String::from(text), text.to_lowercase());
+        let toc = vec![entry(1, "A"), entry(2, "B"), entry(3, "C"), entry(2, "D"), entry(1, "E<")];
+        assert_eq!(
+            toc_html(&toc),
+            "<nav class=\"toc\">\n<ul>\n<li><a href=\"#a\">A</a>\n<ul>\n<li><a href=\"#b\">B</a>\
+             \n<ul>\n<li><a href=\"#c\">C</a></li>\n</ul>\n</li>\n<li><a href=\"#d\">D</a></li>\n\
+             </ul>\n</li>\n<li><a href=\"#e<\">E&lt;</a></li>\n</ul>\n</nav>\n"
+        );
+        // headings that start deeper than they continue stay siblings
+        let toc = vec![entry(3, "A"), entry(2, "B")];
+        assert_eq!(
+            toc_html(&toc),
+            "<nav class=\"toc\">\n<ul>\n<li><a href=\"#a\">A</a></li>\n\
+             <li><a href=\"#b\">B</a></li>\n</ul>\n</nav>\n"
+        );
+        assert_eq!(toc_html(&[]), "<nav class=\"toc\">\n</nav>\n");
+        assert_eq!(insert_toc("<p>[TOC]</p>\n<p>[toc] x</p>\n", "<nav></nav>\n"),
+                   "<nav></nav>\n<p>[toc] x</p>\n");
+    }
+
+    #[test]
+    fn test_definition_lists() {
+        assert_eq!(definition_lists("<p>HTML\n: markup\n: <em>not</em> a\nlanguage</p>\n"),
+                   "<dl>\n<dt>HTML</dt>\n<dd>markup</dd>\n<dd><em>not</em> a\nlanguage</dd>\n</dl>\n");
+        assert_eq!(definition_lists("<p>A\nB\n: both</p>\n\n<p>C\n: c</p>\n<p>text</p>\n"),
+                   "<dl>\n<dt>A</dt>\n<dt>B</dt>\n<dd>both</dd>\n<dt>C</dt>\n<dd>c</dd>\n</dl>\n\
+                    <p>text</p>\n");
+        assert_eq!(definition_lists("<p>: no term</p>\n<p>just\ntext</p>\n"),
+                   "<p>: no term</p>\n<p>just\ntext</p>\n");
+    }
+
+    #[test]
+    fn test_sanitize_urls() {
+        let render = |markdown: &str| {
+            let options = MarkdownOptions { sanitize: true, ..MarkdownOptions::default() };
+            render_html(&Markdown::new(markdown), &options)
+        };
+        assert_eq!(render("[click](javascript:alert(1))"), "<p><a href=\"#\">click</a></p>\n");
+        assert_eq!(render("[x](JavaScript:alert(1)) ![y](data:image/svg+xml,a)"),
+                   "<p><a href=\"#\">x</a> <img src=\"#\" alt=\"y\"></p>\n");
+        assert_eq!(render("[d](data:text/html,a) [v](vbscript:a)"),
+                   "<p><a href=\"#\">d</a> <a href=\"#\">v</a></p>\n");
+        assert_eq!(render("[a](https://a.com) [b](mailto:a@b.c) [c](docs/x:y) [d](/e)"),
+                   "<p><a href=\"https://a.com\">a</a> <a href=\"mailto:a@b.c\">b</a> \
+                    <a href=\"docs/x:y\">c</a> <a href=\"/e\">d</a></p>\n");
+
+        let options = MarkdownOptions::default();
+        assert!(render_html(&Markdown::new("[click](javascript:alert(1))"), &options)
+            .contains("href=\"javascript:alert(1)\""));
+    }
+
+    #[test]
+    fn test_headings() {
+        let html = "<h1>Fish &amp; <em>Chips</em></h1>\n<p>x</p>\n<h2>&#39;a&#x27; <code>&lt;b&gt;</code>\
+                    </h2>\n<hr>\n<h7>no</h7>";
+        let headings: Vec<(u8, String)> = headings(html).into_iter()
+            .map(|heading| (heading.level, heading.text))
+            .collect();
+        assert_eq!(headings, vec![(1, String::from("Fish & Chips")), (2, String::from("'a' <b>"))]);
+    }
+}
@@ -0,0 +1,83 @@
+use super::Page;
+use super::feed::escape;
+
+/// The template used if no other template is configured
+const DEFAULT_TEMPLATE: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{{title}}</title>
+</head>
+<body>
+<main>
+{{content}}</main>
+</body>
+</html>
+";
+
+/// A template used to turn the HTML of a page into a complete document.
+/// The placeholders `{{title}}`, `{{url}}` and `{{content}}` are replaced
+/// with the escaped title, the escaped URL and the HTML of the page
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    /// the source of the template including the placeholders
+    source: String,
+}
+
+impl Template {
+    /// Creates a new template from the given source
+    pub fn new(source: &str) -> Template {
+        Template { source: String::from(source) }
+    }
+
+    /// Renders the page using this template, placeholders are only
+    /// replaced within the template itself and not within the page
+    pub fn render(&self, page: &Page) -> String {
+        let mut output = String::with_capacity(self.source.len() + page.markdown_raw.len());
+        let mut rest = self.source.as_str();
+        while let Some(start) = rest.find("{{") {
+            output.push_str(&rest[..start]);
+            let end = match rest[start..].find("}}") {
+                Some(end) => start + end + 2,
+                None => {
+                    rest = &rest[start..];
+                    break;
+                }
+            };
+            match rest[start + 2..end - 2].trim() {
+                "title" => output.push_str(escape(page.title().as_str()).as_str()),
+                "url" => output.push_str(escape(page.url.as_str()).as_str()),
+                "content" => output.push_str(page.html()),
+                _ => output.push_str(&rest[start..end])
+            }
+            rest = &rest[end..];
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+impl Default for Template {
+    fn default() -> Template {
+        Template::new(DEFAULT_TEMPLATE)
+    }
+}
+
+/// Renders a document that immediately redirects to the given URL and
+/// names it as the canonical location of its content
+pub fn redirect(href: &str) -> String {
+    let href = escape(href);
+    format!("<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta http-equiv=\"refresh\" content=\"0; url={0}\">
+<link rel=\"canonical\" href=\"{0}\">
+<title>Redirecting</title>
+</head>
+<body>
+<p><a href=\"{0}\">{0}</a></p>
+</body>
+</html>
+", href)
+}
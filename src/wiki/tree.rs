@@ -0,0 +1,56 @@
+use super::Page;
+
+/// A node in the hierarchy of the pages of a wiki, every URL segment is a
+/// node of its own
+#[derive(Clone)]
+pub struct TreeNode<'a> {
+    /// the URL segment of this node, empty for the root
+    pub name: String,
+    /// the page with the URL of this node, if there is one
+    pub page: Option<&'a Page>,
+    /// the nodes below this one, sorted by their name
+    pub children: Vec<TreeNode<'a>>,
+}
+
+impl<'a> TreeNode<'a> {
+    /// Creates a node without a page and without children
+    fn new(name: &str) -> TreeNode<'a> {
+        TreeNode { name: String::from(name), page: None, children: Vec::new() }
+    }
+
+    /// Returns whether there are no nodes below this one
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Returns the child node with the given name, if there is one
+    pub fn child(&self, name: &str) -> Option<&TreeNode<'a>> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Returns the child node with the given name, creating it if needed
+    fn child_mut(&mut self, name: &str) -> &mut TreeNode<'a> {
+        let index = match self.children.binary_search_by(|child| child.name.as_str().cmp(name)) {
+            Ok(index) => index,
+            Err(index) => {
+                self.children.insert(index, TreeNode::new(name));
+                index
+            }
+        };
+        &mut self.children[index]
+    }
+}
+
+/// Organizes the given pages into a tree by the segments of their URLs
+pub fn build<'a, I>(pages: I) -> TreeNode<'a>
+    where I: IntoIterator<Item = &'a Page> {
+    let mut root = TreeNode::new("");
+    for page in pages {
+        let mut node = &mut root;
+        for segment in page.url.split('/').filter(|s| !s.is_empty()) {
+            node = node.child_mut(segment);
+        }
+        node.page = Some(page);
+    }
+    root
+}
@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use notify::{self, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as NotifyWatcher};
+use notify::event::{ModifyKind, RenameMode};
+
+use super::WikiError;
+
+/// How long to wait for further changes before handing a batch of changes
+/// to the wiki, editors tend to write files several times when saving
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// A change to the pages of a wiki that has been picked up on disk
+#[derive(Clone, Debug, PartialEq)]
+pub enum WikiEvent {
+    /// a page with the given URL has been added
+    Created(String),
+    /// the page with the given URL has been changed
+    Modified(String),
+    /// the page with the given URL has been removed
+    Deleted(String),
+    /// the page with the first URL has been moved to the second URL
+    Renamed(String, String),
+}
+
+/// A change to the files of the wiki
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    /// the file at the path has been created, modified or removed
+    Path(PathBuf),
+    /// the file at the first path has been moved to the second path
+    Rename(PathBuf, PathBuf),
+}
+
+/// Watches the directory of a wiki for changes
+pub struct Watcher {
+    /// keeps watching the directory until it is dropped
+    _watcher: RecommendedWatcher,
+    /// the debounced batches of changes, behind a mutex to keep the wiki
+    /// `Sync`
+    changes: Mutex<Receiver<Vec<Change>>>,
+}
+
+/// Converts a file system event to the changes to the wiki
+fn changes(event: Event) -> Vec<Change> {
+    match event.kind {
+        EventKind::Access(_) | EventKind::Other => Vec::new(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            let mut paths = event.paths;
+            let to = paths.pop().unwrap();
+            let from = paths.pop().unwrap();
+            vec![Change::Rename(from, to)]
+        }
+        _ => event.paths.into_iter().map(Change::Path).collect()
+    }
+}
+
+/// Collects the changes until no further changes come in for `DEBOUNCE`
+/// and sends them as one batch with all duplicates removed. Both sides of
+/// a rename are also reported on their own, those are dropped as well
+fn debounce(raw: Receiver<Vec<Change>>, batches: Sender<Vec<Change>>) {
+    while let Ok(first) = raw.recv() {
+        let mut batch = first;
+        let disconnected = loop {
+            match raw.recv_timeout(DEBOUNCE) {
+                Ok(changes) => batch.extend(changes),
+                Err(RecvTimeoutError::Timeout) => break false,
+                Err(RecvTimeoutError::Disconnected) => break true
+            }
+        };
+        let renamed: Vec<PathBuf> = batch.iter()
+            .flat_map(|change| match *change {
+                Change::Rename(ref from, ref to) => vec![from.clone(), to.clone()],
+                Change::Path(_) => Vec::new()
+            })
+            .collect();
+        let mut unique: Vec<Change> = Vec::with_capacity(batch.len());
+        for change in batch {
+            let part_of_rename = match change {
+                Change::Path(ref path) => renamed.contains(path),
+                Change::Rename(..) => false
+            };
+            if !part_of_rename && !unique.contains(&change) {
+                unique.push(change);
+            }
+        }
+        if batches.send(unique).is_err() || disconnected {
+            return;
+        }
+    }
+}
+
+impl Watcher {
+    /// Starts watching the given directory recursively
+    /// # Errors
+    /// This will return `WikiError::Watch` if the directory can not be
+    /// watched
+    pub fn start(path: &Path) -> Result<Watcher, WikiError> {
+        let (raw_tx, raw_rx) = mpsc::channel();
+        let (batch_tx, batch_rx) = mpsc::channel();
+
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                let _ = raw_tx.send(changes(event));
+            }
+        }).map_err(|e| WikiError::Watch(e.to_string()))?;
+        watcher.watch(path, RecursiveMode::Recursive)
+            .map_err(|e| WikiError::Watch(e.to_string()))?;
+        thread::spawn(move || debounce(raw_rx, batch_tx));
+
+        Ok(Watcher {
+            _watcher: watcher,
+            changes: Mutex::new(batch_rx),
+        })
+    }
+
+    /// Returns all changes that have been picked up so far
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        match self.changes.get_mut() {
+            Ok(changes) => changes.try_iter().flatten().collect(),
+            Err(_) => Vec::new()
+        }
+    }
+
+    /// Waits until changes have been picked up or the deadline has passed,
+    /// returns all changes that have been picked up so far
+    pub fn wait_for_changes(&mut self, deadline: Instant) -> Vec<Change> {
+        let changes = match self.changes.get_mut() {
+            Ok(changes) => changes,
+            Err(_) => return Vec::new()
+        };
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match changes.recv_timeout(timeout) {
+            Ok(first) => first.into_iter().chain(changes.try_iter().flatten()).collect(),
+            Err(_) => Vec::new()
+        }
+    }
+}